@@ -0,0 +1,69 @@
+//! Fills a long scrollback history and reports how much memory it takes, checked against the
+//! per-cell budget in `term::CELL_SIZE_BUDGET`, exits with an error when a width goes over it.
+//! Run with `cargo bench --bench memory`.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use rust_terminal::color::CellColor;
+use rust_terminal::grid::{Grid, Row};
+use rust_terminal::term::{CharacterCell, Flags, CELL_SIZE_BUDGET};
+
+/// Lines of history kept for every width
+const LINES: usize = 100_000;
+const SCREEN_HEIGHT: usize = 24;
+
+/// Counts the bytes currently allocated on the heap
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Bytes a grid of `width` columns takes once `LINES` lines have scrolled into history
+fn history_size(width: usize) -> usize {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let blank = CharacterCell::new(' ', CellColor::FG, CellColor::BG, Flags::default());
+    let mut grid = Grid::new(width, SCREEN_HEIGHT, LINES, blank);
+    let text = CharacterCell::new('x', CellColor::Indexed(3), CellColor::Rgb(10, 20, 30), Flags::BOLD);
+    for _ in 0..LINES {
+        grid[SCREEN_HEIGHT - 1].fill(text);
+        grid.scroll_up(0..SCREEN_HEIGHT, 1, blank);
+    }
+    let size = ALLOCATED.load(Ordering::Relaxed) - before;
+    drop(grid);
+    size
+}
+
+fn main() {
+    println!("{} bytes per cell, budget {CELL_SIZE_BUDGET}", std::mem::size_of::<CharacterCell>());
+    let mut over_budget = false;
+    for width in [80, 132, 200] {
+        let size = history_size(width);
+        let lines = LINES + SCREEN_HEIGHT;
+        // Each row also needs its `Vec`, the ring buffer can hold up to twice as many as it uses
+        let budget = lines * (width * CELL_SIZE_BUDGET + 2 * std::mem::size_of::<Row>());
+        over_budget |= size > budget;
+        println!(
+            "{width:>3} columns  {lines} lines  {:>7.1} MiB  {:>5.1} bytes per cell{}",
+            size as f64 / (1 << 20) as f64,
+            size as f64 / (lines * width) as f64,
+            if size > budget { "  over budget" } else { "" },
+        );
+    }
+    if over_budget {
+        println!("budget is {CELL_SIZE_BUDGET} bytes per cell");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,104 @@
+//! Feeds large amounts of output through `Terminal` and reports how fast it gets parsed, the
+//! way `yes`, `find /` or a colored build log would hit it. Run with `cargo bench --bench
+//! throughput`, any argument filters the workloads by name. Exits with an error if a workload
+//! is below the target.
+use std::time::{Duration, Instant};
+use rust_terminal::rust_ansi::term::Term;
+use rust_terminal::term::Terminal;
+
+/// Total output per workload
+const INPUT_SIZE: usize = 32 << 20;
+/// Matches what the PTY reader hands over at once
+const CHUNK_SIZE: usize = 1 << 16;
+/// Parsed output per second each workload should reach in a release build, enough to keep up
+/// with a program printing as fast as a PTY lets it
+const TARGET_MIB_PER_SEC: f64 = 50.0;
+
+/// Short lines of plain text, like `yes` or `find /`
+fn plain_lines() -> String {
+    let mut res = String::new();
+    let mut i = 0;
+    while res.len() < INPUT_SIZE {
+        res.push_str(&format!("./src/some/directory/file_{i}.rs\r\n"));
+        i += 1;
+    }
+    res
+}
+
+/// Lines full of color changes, like `ls --color` or a compiler's diagnostics
+fn colored_lines() -> String {
+    let mut res = String::new();
+    let mut i = 0;
+    while res.len() < INPUT_SIZE {
+        res.push_str(&format!(
+            "\x1b[1;3{}mwarning\x1b[0m: unused variable \x1b[38;5;{}m`x_{i}`\x1b[0m at \x1b[38;2;{};100;200mline {i}\x1b[m\r\n",
+            i % 8,
+            i % 256,
+            i % 256,
+        ));
+        i += 1;
+    }
+    res
+}
+
+/// Full screen redraws with absolute positioning, like `htop` or a text editor
+fn screen_redraws() -> String {
+    let mut res = String::new();
+    let mut i = 0;
+    while res.len() < INPUT_SIZE {
+        res.push_str("\x1b[H");
+        for row in 1..=24 {
+            res.push_str(&format!("\x1b[{row};1H\x1b[K\x1b[3{}m{:>5} {:<60}\x1b[m", row % 8, i + row, "some process name"));
+        }
+        i += 1;
+    }
+    res
+}
+
+fn run(input: &str) -> Duration {
+    let mut term = Term::new(Box::new(Terminal::new(80, 24)));
+    let start = Instant::now();
+    let mut rest = input;
+    while !rest.is_empty() {
+        let mut end = CHUNK_SIZE.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end += 1;
+        }
+        let (chunk, tail) = rest.split_at(end);
+        term.write(chunk.to_string());
+        // The front end draws at most once a frame, which is after many chunks during a flood
+        term.completed_render();
+        rest = tail;
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let filter: Vec<String> = std::env::args().skip(1).filter(|arg| !arg.starts_with('-')).collect();
+    let workloads = [
+        ("plain_lines", plain_lines as fn() -> String),
+        ("colored_lines", colored_lines),
+        ("screen_redraws", screen_redraws),
+    ];
+
+    let mut below_target = false;
+    for (name, generate) in workloads {
+        if !filter.is_empty() && !filter.iter().any(|f| name.contains(f.as_str())) {
+            continue;
+        }
+        let input = generate();
+        let elapsed = run(&input);
+        let rate = input.len() as f64 / (1 << 20) as f64 / elapsed.as_secs_f64();
+        below_target |= rate < TARGET_MIB_PER_SEC;
+        println!(
+            "{name:<16} {:>6} MiB in {:>8.3?}  {rate:>8.1} MiB/s{}",
+            input.len() >> 20,
+            elapsed,
+            if rate < TARGET_MIB_PER_SEC { "  below target" } else { "" },
+        );
+    }
+    if below_target {
+        println!("target is {TARGET_MIB_PER_SEC} MiB/s");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,14 @@
+//! Arbitrary output from a program, all in one write like `cat` of a binary file
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_terminal::rust_ansi::term::Term;
+use rust_terminal::term::Terminal;
+use rust_terminal::tty::Utf8Decoder;
+
+fuzz_target!(|data: &[u8]| {
+    let mut term = Term::new(Box::new(Terminal::new(80, 24)));
+    term.write(Utf8Decoder::default().decode(data));
+    let _ = term.framebuffer().take_events();
+    term.completed_render();
+});
@@ -0,0 +1,29 @@
+//! Output split over several reads, so sequences get cut in the middle, on a small screen that
+//! is resized along the way, decoded the way the front end decodes pty reads. The first two
+//! bytes pick the size.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_terminal::rust_ansi::term::Term;
+use rust_terminal::term::Terminal;
+use rust_terminal::tty::Utf8Decoder;
+
+fuzz_target!(|data: &[u8]| {
+    let (size, data) = match data {
+        [width, height, rest @ ..] => ((*width as usize % 16 + 1, *height as usize % 8 + 1), rest),
+        _ => return,
+    };
+    let mut term = Term::new(Box::new(Terminal::new(size.0, size.1)));
+    let mut decoder = Utf8Decoder::default();
+    // A 0xff byte ends a chunk, the byte after it resizes the screen
+    for (i, chunk) in data.split(|&b| b == 0xff).enumerate() {
+        if i > 0 {
+            if let Some(&b) = chunk.first() {
+                term.framebuffer().request_resize(b as usize % 16 + 1, (b >> 4) as usize % 8 + 1);
+            }
+        }
+        term.write(decoder.decode(chunk));
+        let _ = term.framebuffer().take_events();
+        term.completed_render();
+    }
+});
@@ -0,0 +1,209 @@
+use rust_terminal::color::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::WindowCanvas;
+
+// Line weights of U+2500 to U+257F, one nibble per arm in the order up, right, down, left.
+// 1 is light, 2 heavy and 3 double.
+const BOX_LINES: [u16; 0x80] = [
+    0x0101, 0x0202, 0x1010, 0x2020, 0x0101, 0x0202, 0x1010, 0x2020,
+    0x0101, 0x0202, 0x1010, 0x2020, 0x0110, 0x0210, 0x0120, 0x0220,
+    0x0011, 0x0012, 0x0021, 0x0022, 0x1100, 0x1200, 0x2100, 0x2200,
+    0x1001, 0x1002, 0x2001, 0x2002, 0x1110, 0x1210, 0x2110, 0x1120,
+    0x2120, 0x2210, 0x1220, 0x2220, 0x1011, 0x1012, 0x2011, 0x1021,
+    0x2021, 0x2012, 0x1022, 0x2022, 0x0111, 0x0112, 0x0211, 0x0212,
+    0x0121, 0x0122, 0x0221, 0x0222, 0x1101, 0x1102, 0x1201, 0x1202,
+    0x2101, 0x2102, 0x2201, 0x2202, 0x1111, 0x1112, 0x1211, 0x1212,
+    0x2111, 0x1121, 0x2121, 0x2112, 0x2211, 0x1122, 0x1221, 0x2212,
+    0x1222, 0x2122, 0x2221, 0x2222, 0x0101, 0x0202, 0x1010, 0x2020,
+    0x0303, 0x3030, 0x0310, 0x0130, 0x0330, 0x0013, 0x0031, 0x0033,
+    0x1300, 0x3100, 0x3300, 0x1003, 0x3001, 0x3003, 0x1310, 0x3130,
+    0x3330, 0x1013, 0x3031, 0x3033, 0x0313, 0x0131, 0x0333, 0x1303,
+    0x3101, 0x3303, 0x1313, 0x3131, 0x3333, 0x0110, 0x0011, 0x1001,
+    0x1100, 0x0000, 0x0000, 0x0000, 0x0001, 0x1000, 0x0100, 0x0010,
+    0x0002, 0x2000, 0x0200, 0x0020, 0x0201, 0x1020, 0x0102, 0x2010,
+];
+
+const LIGHT: u16 = 1;
+const HEAVY: u16 = 2;
+const DOUBLE: u16 = 3;
+
+/// Draws box drawing characters, block elements, braille and the powerline separators without
+/// the font so they fill `cell` exactly and line up with their neighbours. The background has
+/// to be drawn already. Returns false if `ch` isn't one of them.
+pub fn draw(canvas: &mut WindowCanvas, cell: Rect, ch: char, fgc: Color, bgc: Color) -> bool {
+    let mut rects = Vec::new();
+    let mut lines = Vec::new();
+    let mut color = fgc;
+    match ch as u32 {
+        0x2571..=0x2573 => {
+            let (x, y, r, b) = (cell.x(), cell.y(), cell.right() - 1, cell.bottom() - 1);
+            if ch != '\u{2572}' {
+                lines.push((Point::new(r, y), Point::new(x, b)));
+            }
+            if ch != '\u{2571}' {
+                lines.push((Point::new(x, y), Point::new(r, b)));
+            }
+        }
+        cp @ 0x2500..=0x257f => box_lines(cell, ch, BOX_LINES[(cp - 0x2500) as usize], &mut rects),
+        0x2591..=0x2593 => {
+            // Shades are drawn as a solid mix of the colors, a dither pattern flickers when scaled
+            rects.push(cell);
+            color = mix(fgc, bgc, (ch as u32 - 0x2590) as u8 * 64);
+        }
+        cp @ 0x2580..=0x259f => block(cell, cp, &mut rects),
+        cp @ 0x2800..=0x28ff => braille(cell, cp as u8, &mut rects),
+        0xe0b0 | 0xe0b2 => triangle(cell, ch == '\u{e0b2}', &mut rects),
+        0xe0b1 | 0xe0b3 => {
+            let (x, r) = if ch == '\u{e0b1}' { (cell.x(), cell.right() - 1) } else { (cell.right() - 1, cell.x()) };
+            let mid = Point::new(r, cell.y() + cell.height() as i32 / 2);
+            lines.push((Point::new(x, cell.y()), mid));
+            lines.push((mid, Point::new(x, cell.bottom() - 1)));
+        }
+        _ => return false,
+    }
+    canvas.set_draw_color(color);
+    canvas.fill_rects(&rects).unwrap();
+    for (start, end) in lines {
+        canvas.draw_line(start, end).unwrap();
+    }
+    true
+}
+
+/// `amount` out of 256 of `fgc` over `bgc`
+fn mix(fgc: Color, bgc: Color, amount: u8) -> Color {
+    let channel = |f: u8, b: u8| ((f as u32 * amount as u32 + b as u32 * (256 - amount as u32)) / 256) as u8;
+    Color::RGB(channel(fgc.r, bgc.r), channel(fgc.g, bgc.g), channel(fgc.b, bgc.b))
+}
+
+/// A rect from `x0..x1` and `y0..y1`, or nothing if that's empty since SDL would make it 1 pixel
+fn span(x0: i32, y0: i32, x1: i32, y1: i32) -> Option<Rect> {
+    if x1 > x0 && y1 > y0 {
+        Some(Rect::new(x0, y0, (x1 - x0) as u32, (y1 - y0) as u32))
+    } else {
+        None
+    }
+}
+
+fn box_lines(cell: Rect, ch: char, weights: u16, rects: &mut Vec<Rect>) {
+    let [up, right, down, left] = [12, 8, 4, 0].map(|shift| (weights >> shift) & 0xf);
+    let light = (cell.width() as i32 / 8).max(1);
+    // Distance from the middle of the cell to each edge of a stroke of the given weight, double
+    // lines are two light strokes with a light gap between them
+    let extent = |weight: u16| match weight {
+        0 => (0, 0),
+        LIGHT => (light / 2, light - light / 2),
+        HEAVY => (light, light),
+        _ => (light + light / 2, 2 * light - light / 2),
+    };
+    let strokes = |weight: u16| -> Vec<(i32, i32)> {
+        let (before, after) = extent(weight);
+        if weight == DOUBLE {
+            vec![(-before, -before + light), (after - light, after)]
+        } else {
+            vec![(-before, after)]
+        }
+    };
+    let (mx, my) = (cell.x() + cell.width() as i32 / 2, cell.y() + cell.height() as i32 / 2);
+    // Arms reach past the middle far enough to cover the strokes they meet
+    let horizontal = extent(left.max(right));
+    let vertical = extent(up.max(down));
+    let dashes = match ch as u32 {
+        0x2504..=0x2507 => 3,
+        0x2508..=0x250b => 4,
+        0x254c..=0x254f => 2,
+        _ => 1,
+    };
+
+    for (weight, from, to) in [(left, cell.x(), mx + vertical.1), (right, mx - vertical.0, cell.right())] {
+        for (a, b) in strokes(weight) {
+            dashed(from, to, dashes, |x0, x1| span(x0, my + a, x1, my + b), rects);
+        }
+    }
+    for (weight, from, to) in [(up, cell.y(), my + horizontal.1), (down, my - horizontal.0, cell.bottom())] {
+        for (a, b) in strokes(weight) {
+            dashed(from, to, dashes, |y0, y1| span(mx + a, y0, mx + b, y1), rects);
+        }
+    }
+}
+
+/// Splits `from..to` into `dashes` dashes with gaps between them
+fn dashed(from: i32, to: i32, dashes: i32, rect: impl Fn(i32, i32) -> Option<Rect>, rects: &mut Vec<Rect>) {
+    if dashes == 1 {
+        rects.extend(rect(from, to));
+        return;
+    }
+    let len = to - from;
+    for i in 0..dashes {
+        let start = from + len * i / dashes;
+        let end = from + len * (i + 1) / dashes;
+        let gap = (end - start) / 3;
+        rects.extend(rect(start + gap / 2, end - (gap - gap / 2)));
+    }
+}
+
+fn block(cell: Rect, cp: u32, rects: &mut Vec<Rect>) {
+    let (x, y, r, b) = (cell.x(), cell.y(), cell.right(), cell.bottom());
+    let (w, h) = (cell.width() as i32, cell.height() as i32);
+    let (mx, my) = (x + w / 2, y + h / 2);
+    let quadrants = |ul: bool, ur: bool, ll: bool, lr: bool, rects: &mut Vec<Rect>| {
+        for (on, x0, y0, x1, y1) in [(ul, x, y, mx, my), (ur, mx, y, r, my), (ll, x, my, mx, b), (lr, mx, my, r, b)] {
+            if on {
+                rects.extend(span(x0, y0, x1, y1));
+            }
+        }
+    };
+    match cp {
+        0x2580 => rects.extend(span(x, y, r, my)),
+        // Lower one eighth up to the full block
+        0x2581..=0x2588 => rects.extend(span(x, b - h * (cp - 0x2580) as i32 / 8, r, b)),
+        // Left seven eighths down to one eighth
+        0x2589..=0x258f => rects.extend(span(x, y, x + w * (0x2590 - cp) as i32 / 8, b)),
+        0x2590 => rects.extend(span(mx, y, r, b)),
+        0x2594 => rects.extend(span(x, y, r, y + h / 8)),
+        0x2595 => rects.extend(span(r - w / 8, y, r, b)),
+        0x2596 => quadrants(false, false, true, false, rects),
+        0x2597 => quadrants(false, false, false, true, rects),
+        0x2598 => quadrants(true, false, false, false, rects),
+        0x2599 => quadrants(true, false, true, true, rects),
+        0x259a => quadrants(true, false, false, true, rects),
+        0x259b => quadrants(true, true, true, false, rects),
+        0x259c => quadrants(true, true, false, true, rects),
+        0x259d => quadrants(false, true, false, false, rects),
+        0x259e => quadrants(false, true, true, false, rects),
+        _ => quadrants(false, true, true, true, rects),
+    }
+}
+
+fn braille(cell: Rect, dots: u8, rects: &mut Vec<Rect>) {
+    // Bit i of the code point is dot i + 1, numbered down the left column then the right one,
+    // except dots 7 and 8 which were added at the bottom later
+    const POSITIONS: [(i32, i32); 8] = [(0, 0), (0, 1), (0, 2), (1, 0), (1, 1), (1, 2), (0, 3), (1, 3)];
+    let (w, h) = (cell.width() as i32, cell.height() as i32);
+    let size = (w / 4).min(h / 8).max(1);
+    for (bit, (col, row)) in POSITIONS.iter().enumerate() {
+        if dots & (1 << bit) == 0 {
+            continue;
+        }
+        // Centre the dot in its half of the width and quarter of the height
+        let x = cell.x() + w * (2 * col + 1) / 4 - size / 2;
+        let y = cell.y() + h * (2 * row + 1) / 8 - size / 2;
+        rects.extend(span(x, y, x + size, y + size));
+    }
+}
+
+/// Solid powerline arrow pointing right, or left if `left`
+fn triangle(cell: Rect, left: bool, rects: &mut Vec<Rect>) {
+    let (w, h) = (cell.width() as i32, cell.height() as i32);
+    for row in 0..h {
+        // Distance from the middle row, in half pixels so both halves come out symmetrical
+        let dist = (2 * row + 1 - h).abs();
+        let len = w * (h - dist) / h;
+        let y = cell.y() + row;
+        let rect = if left {
+            span(cell.right() - len, y, cell.right(), y + 1)
+        } else {
+            span(cell.x(), y, cell.x() + len, y + 1)
+        };
+        rects.extend(rect);
+    }
+}
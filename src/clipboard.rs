@@ -0,0 +1,167 @@
+use sdl2::clipboard::ClipboardUtil;
+use rust_terminal::tty::ForkPTY;
+
+const PASTE_START: &str = "\x1b[200~";
+const PASTE_END: &str = "\x1b[201~";
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// What programs may do with the clipboard through OSC 52. Reading is off by default since
+/// anything running in the terminal, like a remote host over ssh, could grab the clipboard.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Osc52Policy {
+    Deny,
+    Write,
+    ReadWrite,
+}
+
+impl std::str::FromStr for Osc52Policy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "deny" => Ok(Osc52Policy::Deny),
+            "write" => Ok(Osc52Policy::Write),
+            "read-write" => Ok(Osc52Policy::ReadWrite),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Sends the clipboard contents to the pty, wrapped in bracketed paste markers if the
+/// application asked for them.
+pub fn paste(clipboard: &ClipboardUtil, con: &ForkPTY, bracketed: bool) {
+    if !clipboard.has_clipboard_text() {
+        return;
+    }
+    match clipboard.clipboard_text() {
+        Ok(text) => {
+            if let Err(e) = con.write_bytes(prepare_paste(&text, bracketed).as_bytes()) {
+                eprintln!("Warning: Could not paste {} bytes: {e}", text.len());
+            }
+        }
+        Err(e) => eprintln!("Warning: Could not read clipboard: {e}"),
+    }
+}
+
+/// Puts `text` on the clipboard. sdl2 doesn't expose the X11 primary selection, so that one is
+/// left alone.
+pub fn copy(clipboard: &ClipboardUtil, text: &str) {
+    if let Err(e) = clipboard.set_clipboard_text(text) {
+        eprintln!("Warning: Could not set clipboard: {e}");
+    }
+}
+
+pub fn prepare_paste(text: &str, bracketed: bool) -> String {
+    let text = strip_escapes(text);
+    if bracketed {
+        format!("{PASTE_START}{text}{PASTE_END}")
+    } else {
+        text
+    }
+}
+
+/// Removes escape sequences and control characters so pasted text can't drive the terminal
+/// (or end the bracketed paste early). Newlines are sent as carriage returns like a keypress.
+pub fn strip_escapes(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\x1b' => {
+                match chars.next() {
+                    // CSI: parameters and intermediates up to a final byte
+                    Some('[') => {
+                        for c in chars.by_ref() {
+                            if ('\x40'..='\x7e').contains(&c) {
+                                break;
+                            }
+                        }
+                    }
+                    // OSC, DCS, SOS, PM and APC: everything up to BEL or ST
+                    Some(']') | Some('P') | Some('X') | Some('^') | Some('_') => {
+                        while let Some(c) = chars.next() {
+                            if c == '\x07' {
+                                break;
+                            }
+                            if c == '\x1b' && chars.peek() == Some(&'\\') {
+                                chars.next();
+                                break;
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            '\r' => {
+                if chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                res.push('\r');
+            }
+            '\n' => res.push('\r'),
+            '\t' => res.push('\t'),
+            c if c.is_control() => {}
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+/// Copies the base64 `payload` of an OSC 52 sequence, unless it decodes to more than `max_bytes`
+pub fn osc52_store(clipboard: &ClipboardUtil, payload: &str, max_bytes: usize) {
+    // Checked before decoding so a huge payload isn't decoded just to be dropped
+    if payload.len() / 4 * 3 > max_bytes {
+        eprintln!("Warning: Ignoring OSC 52 copy of {} bytes", payload.len() / 4 * 3);
+        return;
+    }
+    match base64_decode(payload).map(String::from_utf8) {
+        Some(Ok(text)) => copy(clipboard, &text),
+        _ => eprintln!("Warning: Ignoring OSC 52 copy that isn't base64 encoded UTF-8"),
+    }
+}
+
+/// Answers an OSC 52 query with the clipboard contents, or nothing if they are over `max_bytes`
+pub fn osc52_load(clipboard: &ClipboardUtil, con: &ForkPTY, max_bytes: usize) {
+    let text = match clipboard.clipboard_text() {
+        Ok(text) if text.len() <= max_bytes => text,
+        Ok(_) => return,
+        Err(e) => {
+            eprintln!("Warning: Could not read clipboard: {e}");
+            return;
+        }
+    };
+    con.write(format!("\x1b]52;c;{}\x07", base64_encode(text.as_bytes())));
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut res = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                res.push(BASE64_CHARS[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                res.push('=');
+            }
+        }
+    }
+    res
+}
+
+/// Decodes standard base64, the padding is optional
+fn base64_decode(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim_end_matches('=');
+    let mut res = Vec::with_capacity(s.len() / 4 * 3 + 2);
+    let (mut bits, mut n) = (0u32, 0u32);
+    for ch in s.bytes() {
+        let value = BASE64_CHARS.iter().position(|c| *c == ch)? as u32;
+        bits = bits << 6 | value;
+        n += 6;
+        if n >= 8 {
+            n -= 8;
+            res.push((bits >> n) as u8);
+            bits &= (1 << n) - 1;
+        }
+    }
+    Some(res)
+}
@@ -0,0 +1,89 @@
+/// An RGBA color, laid out like SDL's so the front end can convert it for free
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const WHITE: Color = Color::RGB(255, 255, 255);
+    pub const BLACK: Color = Color::RGB(0, 0, 0);
+
+    // Named like SDL's constructor
+    #[allow(non_snake_case)]
+    pub const fn RGB(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 0xff }
+    }
+}
+
+#[cfg(feature = "sdl-frontend")]
+impl From<Color> for sdl2::pixels::Color {
+    fn from(c: Color) -> Self {
+        sdl2::pixels::Color::RGBA(c.r, c.g, c.b, c.a)
+    }
+}
+
+pub const COL_ARR: [Color; 8] = [
+    Color {r: 0x23, g: 0x26, b: 0x27, a: 0},     // BLACK
+    Color {r: 0xed, g: 0x15, b: 0x15, a: 0},   // RED
+    Color {r: 0x11, g: 0xd1, b: 0x16, a: 0},   // GREEN
+    Color {r: 0xf6, g: 0x74, b: 0x00, a: 0}, // YELLOW
+    Color {r: 0x1d, g: 0x99, b: 0xf3, a: 0},   // BLUE
+    Color {r: 0x9b, g: 0x59, b: 0xb6, a: 0}, // MAGENTA
+    Color {r: 0x1a, g: 0xbc, b: 0x9c, a: 0}, // CYAN
+    Color {r: 0xfc, g: 0xfc, b: 0xfc, a: 0}, // WHITE
+];
+pub const BOLD_COL_ARR: [Color; 8] = [
+    Color {r: 0x7f, g: 0x8c, b: 0x8d, a: 0},// BLACK
+    Color {r: 0xc0, g: 0x39, b: 0x2b, a: 0},   // RED
+    Color {r: 0x1c, g: 0xdc, b: 0x9a, a: 0},   // GREEN
+    Color {r: 0xfd, g: 0xbc, b: 0x4b, a: 0}, // YELLOW
+    Color {r: 0x3d, g: 0xae, b: 0xe9, a: 0},   // BLUE
+    Color {r: 0x8e, g: 0x44, b: 0xad, a: 0}, // MAGENTA
+    Color {r: 0x16, g: 0xa0, b: 0x85, a: 0}, // CYAN
+    Color {r: 0xff, g: 0xff, b: 0xff, a: 0}, // WHITE
+];
+
+pub const BG_COL: Color = COL_ARR[0];
+pub const FG_COL: Color = COL_ARR[7];
+pub const BOLD_FG_COL: Color = BOLD_COL_ARR[7];
+
+/// A color as cells store it, either an entry of the 256 color palette or a direct RGB value.
+/// It takes 4 bytes and palette entries are only looked up when the cell is drawn.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum CellColor {
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl CellColor {
+    pub const FG: CellColor = CellColor::Indexed(7);
+    pub const BG: CellColor = CellColor::Indexed(0);
+
+    pub fn rgb(self) -> Color {
+        match self {
+            CellColor::Indexed(index) => palette(index),
+            CellColor::Rgb(r, g, b) => Color::RGB(r, g, b),
+        }
+    }
+}
+
+/// Entry `index` of the 256 color palette: the 8 normal and 8 bright colors, a 6x6x6 color cube
+/// and 24 grays
+pub fn palette(index: u8) -> Color {
+    match index {
+        0..=7 => COL_ARR[index as usize],
+        8..=15 => BOLD_COL_ARR[index as usize - 8],
+        16..=231 => {
+            let c = index - 16;
+            let level = |i: u8| 55 + i * 40;
+            Color::RGB(level(c / 36), level(c % 36 / 6), level(c % 6))
+        }
+        _ => {
+            let c = 0x8 + (index - 232) * 0xA;
+            Color::RGB(c, c, c)
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::path::PathBuf;
+use crate::clipboard::Osc52Policy;
+use crate::hints::HintRule;
+use rust_terminal::term::HISTORY_LIMIT;
+
+/// User settings, read from `$XDG_CONFIG_HOME/rust_terminal/config` (or
+/// `~/.config/rust_terminal/config`). The file holds one `key = value` pair per line, lines
+/// starting with `#` are comments and anything not set keeps its default.
+pub struct Config {
+    /// Jump back to the bottom of the scrollback when the program prints something
+    pub scroll_to_bottom_on_output: bool,
+    /// Lines scrolled per mouse wheel step
+    pub wheel_scroll_lines: usize,
+    /// Time the cursor stays on and off while blinking, 0 turns blinking off
+    pub cursor_blink_interval_ms: u64,
+    /// Font family, any installed monospace font is used if it can't be found
+    pub font_family: String,
+    /// Font size in points
+    pub font_size: u16,
+    /// Zooming resizes the window to keep the number of rows and columns, instead of fitting
+    /// more or fewer cells into the same window
+    pub zoom_resizes_window: bool,
+    /// Families tried in order for characters the main font doesn't have, as a comma separated
+    /// list in the config file
+    pub font_fallback: Vec<String>,
+    /// Draw box drawing, block element, braille and powerline characters to fit the cell
+    /// instead of taking them from the font
+    pub builtin_box_drawing: bool,
+    /// Command Ctrl+click runs with the link appended, it may have arguments of its own
+    pub link_opener: String,
+    /// Command Ctrl+click runs on file paths. `{file}`, `{line}` and `{col}` are replaced, like
+    /// `code --goto {file}:{line}:{col}`, and the path is appended if there's no `{file}`
+    pub file_opener: String,
+    /// What hint mode labels, set with `hint_<name> = <copy|paste|open> <regex>` or turned off
+    /// with `hint_<name> = off`. The defaults are `url`, `path`, `hash` and `ip`.
+    pub hints: Vec<HintRule>,
+    /// Whether programs may copy (`write`), also paste (`read-write`) or neither (`deny`)
+    /// through OSC 52
+    pub osc52: Osc52Policy,
+    /// Largest text OSC 52 copies or pastes, in bytes
+    pub osc52_max_bytes: usize,
+    /// Lines of scrollback history kept, older lines are dropped
+    pub history_limit: usize,
+    /// Window title, `{title}`, `{icon}` and `{cwd}` are replaced with what the program set and
+    /// the shell's working directory
+    pub title_template: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            scroll_to_bottom_on_output: true,
+            wheel_scroll_lines: 3,
+            cursor_blink_interval_ms: 500,
+            font_family: String::from("Hack"),
+            font_size: 15,
+            zoom_resizes_window: false,
+            font_fallback: [
+                "DejaVu Sans Mono",
+                "Noto Sans Mono",
+                "Noto Sans CJK SC",
+                "Noto Sans Symbols",
+                "Noto Sans Symbols2",
+                "Noto Color Emoji",
+                "Symbola",
+            ].iter().map(|family| family.to_string()).collect(),
+            builtin_box_drawing: true,
+            link_opener: String::from("xdg-open"),
+            file_opener: String::from("xdg-open {file}"),
+            hints: HintRule::defaults(),
+            osc52: Osc52Policy::Write,
+            osc52_max_bytes: 1 << 20,
+            history_limit: HISTORY_LIMIT,
+            title_template: String::from("{title}"),
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(dir.join("rust_terminal").join("config"))
+    }
+
+    /// Loads the config file, falling back to the defaults if there isn't one
+    pub fn load() -> Self {
+        let mut config = Self::default();
+        let contents = match Self::path().map(std::fs::read_to_string) {
+            Some(Ok(contents)) => contents,
+            _ => return config,
+        };
+        config.parse(&contents);
+        config
+    }
+
+    pub fn parse(&mut self, contents: &str) {
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let res = match line.split_once('=') {
+                Some((key, value)) => self.set(key.trim(), value.trim()),
+                None => Err(String::from("expected `key = value`")),
+            };
+            if let Err(e) = res {
+                eprintln!("Warning: Config line {}: {e}", i + 1);
+            }
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "scroll_to_bottom_on_output" => self.scroll_to_bottom_on_output = parse_value(value)?,
+            "wheel_scroll_lines" => self.wheel_scroll_lines = parse_value(value)?,
+            "cursor_blink_interval_ms" => self.cursor_blink_interval_ms = parse_value(value)?,
+            "font_family" => self.font_family = value.to_string(),
+            "font_size" => {
+                self.font_size = match parse_value(value)? {
+                    0 => return Err(String::from("font size must be at least 1")),
+                    size => size,
+                }
+            }
+            "zoom_resizes_window" => self.zoom_resizes_window = parse_value(value)?,
+            "font_fallback" => self.font_fallback = parse_list(value),
+            "builtin_box_drawing" => self.builtin_box_drawing = parse_value(value)?,
+            "link_opener" => self.link_opener = value.to_string(),
+            "file_opener" => self.file_opener = value.to_string(),
+            "osc52" => self.osc52 = parse_value(value)?,
+            "osc52_max_bytes" => self.osc52_max_bytes = parse_value(value)?,
+            "history_limit" => self.history_limit = parse_value(value)?,
+            "title_template" => self.title_template = value.to_string(),
+            _ if key.starts_with("hint_") => self.set_hint(&key["hint_".len()..], value)?,
+            _ => return Err(format!("unknown key {key:?}")),
+        }
+        Ok(())
+    }
+
+    fn set_hint(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let index = self.hints.iter().position(|rule| rule.name == name);
+        if value == "off" {
+            if let Some(i) = index {
+                self.hints.remove(i);
+            }
+            return Ok(());
+        }
+        let (action, pattern) = value.split_once(char::is_whitespace).ok_or("expected `<action> <regex>`")?;
+        let rule = HintRule::new(name, parse_value(action)?, pattern.trim()).map_err(|e| e.to_string())?;
+        match index {
+            Some(i) => self.hints[i] = rule,
+            None => self.hints.push(rule),
+        }
+        Ok(())
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value {value:?}"))
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    value.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
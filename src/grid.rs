@@ -0,0 +1,156 @@
+use std::collections::VecDeque;
+use std::ops::{Index, IndexMut, Range};
+use crate::term::CharacterCell;
+
+pub type Row = Vec<CharacterCell>;
+
+/// The screen and the scrollback history above it, kept together in one ring buffer of rows
+/// with the oldest line first. The last `height` rows are the screen, so when the whole screen
+/// scrolls up the top row becomes history without being moved, and once history is full the
+/// line that falls off the top is cleared and reused as the new bottom row.
+pub struct Grid {
+    rows: VecDeque<Row>,
+    width: usize,
+    height: usize,
+    history_limit: usize,
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize, history_limit: usize, blank: CharacterCell) -> Self {
+        Self {
+            rows: (0..height).map(|_| vec![blank; width]).collect(),
+            width,
+            height,
+            history_limit,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Number of lines in history
+    pub fn history_len(&self) -> usize {
+        self.rows.len() - self.height
+    }
+
+    /// Row `y` of the screen, 0 is the top
+    pub fn get(&self, y: usize) -> Option<&Row> {
+        if y >= self.height {
+            return None;
+        }
+        self.rows.get(self.history_len() + y)
+    }
+
+    pub fn get_mut(&mut self, y: usize) -> Option<&mut Row> {
+        if y >= self.height {
+            return None;
+        }
+        let start = self.history_len();
+        self.rows.get_mut(start + y)
+    }
+
+    /// Line `i` counting from the oldest line in history, the screen comes right after it
+    pub fn line(&self, i: usize) -> Option<&Row> {
+        self.rows.get(i)
+    }
+
+    pub fn clear_history(&mut self) {
+        let len = self.history_len();
+        self.rows.drain(..len);
+    }
+
+    /// Scrolls the screen rows in `region` up by `n`, filling the rows that come in at the
+    /// bottom with `blank`. Lines only go into history when the region is the whole screen,
+    /// returns how many did.
+    pub fn scroll_up(&mut self, region: Range<usize>, n: usize, blank: CharacterCell) -> usize {
+        let n = n.min(region.len());
+        if region == (0..self.height) {
+            for _ in 0..n {
+                let mut row = if self.history_len() >= self.history_limit {
+                    self.rows.pop_front().unwrap_or_default()
+                } else {
+                    Vec::new()
+                };
+                fill(&mut row, self.width, blank);
+                self.rows.push_back(row);
+            }
+            return n;
+        }
+        let start = self.history_len() + region.start;
+        let end = self.history_len() + region.end;
+        for _ in 0..n {
+            if let Some(mut row) = self.rows.remove(start) {
+                fill(&mut row, self.width, blank);
+                self.rows.insert(end - 1, row);
+            }
+        }
+        0
+    }
+
+    /// Scrolls the screen rows in `region` down by `n`, filling the rows that come in at the top
+    /// with `blank`. The rows pushed out at the bottom are gone.
+    pub fn scroll_down(&mut self, region: Range<usize>, n: usize, blank: CharacterCell) {
+        let n = n.min(region.len());
+        let start = self.history_len() + region.start;
+        let end = self.history_len() + region.end;
+        for _ in 0..n {
+            if let Some(mut row) = self.rows.remove(end - 1) {
+                fill(&mut row, self.width, blank);
+                self.rows.insert(start, row);
+            }
+        }
+    }
+
+    /// Changes the screen to `width` by `height`. The top `scrolled_off` rows of the screen go
+    /// into history, rows that don't fit below them are dropped and missing ones are added at
+    /// the bottom. Screen rows are cut or padded with `blank`, history keeps its old width and
+    /// nothing is reflowed.
+    pub fn resize(&mut self, width: usize, height: usize, scrolled_off: usize, blank: CharacterCell) {
+        let scrolled_off = scrolled_off.min(self.height);
+        let history = self.history_len() + scrolled_off;
+        self.rows.truncate(history + height);
+        while self.rows.len() < history + height {
+            self.rows.push_back(vec![blank; width]);
+        }
+        self.width = width;
+        self.height = height;
+        let excess = self.history_len().saturating_sub(self.history_limit);
+        self.rows.drain(..excess);
+
+        let start = self.history_len();
+        for row in self.rows.range_mut(start..) {
+            // The wrap flag belongs on whatever is now the last cell
+            let wrap = row.last().is_some_and(|cell| cell.wrap());
+            if let Some(cell) = row.last_mut() {
+                cell.set_wrap(false);
+            }
+            row.resize(width, blank);
+            row[width - 1].set_wrap(wrap);
+        }
+    }
+}
+
+/// Turns `row` into `width` blank cells, reusing its allocation
+fn fill(row: &mut Row, width: usize, blank: CharacterCell) {
+    row.clear();
+    row.resize(width, blank);
+}
+
+impl Index<usize> for Grid {
+    type Output = Row;
+
+    fn index(&self, y: usize) -> &Row {
+        self.get(y).expect("row outside the screen")
+    }
+}
+
+impl IndexMut<usize> for Grid {
+    fn index_mut(&mut self, y: usize) -> &mut Row {
+        self.get_mut(y).expect("row outside the screen")
+    }
+}
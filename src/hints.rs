@@ -0,0 +1,170 @@
+use regex::Regex;
+use sdl2::keyboard::Keycode;
+use crate::links::{self, PATH_PATTERN, URL_PATTERN};
+use rust_terminal::selection::{logical_lines, Point, SelectionRange};
+use rust_terminal::term::CharacterCellManager;
+
+/// Characters labels are made of, the home row comes first
+const LABEL_CHARS: &str = "jfkdlsahgurieowpqvncmxz";
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum HintAction {
+    Copy,
+    /// Type the text into the terminal
+    Paste,
+    /// Open URLs in the browser and paths in the editor
+    Open,
+}
+
+impl std::str::FromStr for HintAction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "copy" => Ok(HintAction::Copy),
+            "paste" => Ok(HintAction::Paste),
+            "open" => Ok(HintAction::Open),
+            _ => Err(()),
+        }
+    }
+}
+
+/// What hint mode looks for and what it does with it. If the regex has a group, the first
+/// group is the text, otherwise the whole match.
+pub struct HintRule {
+    pub name: String,
+    pub action: HintAction,
+    pub regex: Regex,
+}
+
+impl HintRule {
+    pub fn new(name: &str, action: HintAction, pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            name: name.to_string(),
+            action,
+            regex: Regex::new(pattern)?,
+        })
+    }
+
+    pub fn defaults() -> Vec<Self> {
+        [
+            ("url", HintAction::Open, URL_PATTERN),
+            ("path", HintAction::Open, PATH_PATTERN),
+            ("hash", HintAction::Copy, r"\b[0-9a-f]{7,40}\b"),
+            ("ip", HintAction::Copy, r"\b(?:\d{1,3}\.){3}\d{1,3}(?::\d+)?\b"),
+        ].iter()
+            .map(|(name, action, pattern)| Self::new(name, *action, pattern).unwrap())
+            .collect()
+    }
+}
+
+pub struct Hint {
+    pub label: String,
+    pub text: String,
+    pub action: HintAction,
+    pub range: SelectionRange,
+}
+
+pub enum HintInput {
+    None,
+    /// A label was typed in full
+    Selected(Hint),
+    Exit,
+}
+
+/// Labels every match of the hint rules in view so one can be picked from the keyboard
+pub struct HintMode {
+    hints: Vec<Hint>,
+    typed: String,
+}
+
+impl HintMode {
+    /// Finds the hints on the absolute lines `top..=bottom`. Rules earlier in the list win where
+    /// matches overlap, and `accept` can drop matches like paths that don't exist.
+    pub fn new(rules: &[HintRule], cells: &CharacterCellManager, top: usize, bottom: usize, accept: impl Fn(&HintRule, &str) -> bool) -> Self {
+        let mut found: Vec<(SelectionRange, String, HintAction)> = Vec::new();
+        for line in logical_lines(cells, top, bottom) {
+            for rule in rules {
+                for caps in rule.regex.captures_iter(&line.text) {
+                    let m = match caps.get(1).or_else(|| caps.get(0)) {
+                        Some(m) => m,
+                        None => continue,
+                    };
+                    let text = links::trim_url(m.as_str());
+                    let range = match line.range(m.start(), m.start() + text.len()) {
+                        Some(range) => range,
+                        None => continue,
+                    };
+                    let overlaps = found.iter().any(|(other, _, _)| other.start <= range.end && range.start <= other.end);
+                    // Lines wrapped in from above the view can have matches that start off screen
+                    if overlaps || range.start.line < top || !accept(rule, text) {
+                        continue;
+                    }
+                    found.push((range, text.to_string(), rule.action));
+                }
+            }
+        }
+        found.sort_by_key(|(range, _, _)| range.start);
+
+        let labels = labels(found.len());
+        let hints = found.into_iter()
+            .zip(labels)
+            .map(|((range, text, action), label)| Hint { label, text, action, range })
+            .collect();
+        Self { hints, typed: String::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hints.is_empty()
+    }
+
+    pub fn handle_key(&mut self, keycode: Keycode) -> HintInput {
+        match keycode {
+            Keycode::Escape => HintInput::Exit,
+            Keycode::Backspace => {
+                self.typed.pop();
+                HintInput::None
+            }
+            _ => HintInput::None,
+        }
+    }
+
+    pub fn handle_char(&mut self, ch: char) -> HintInput {
+        self.typed.push(ch.to_ascii_lowercase());
+        match self.hints.iter().position(|hint| hint.label == self.typed) {
+            Some(i) => HintInput::Selected(self.hints.swap_remove(i)),
+            // Typos are dropped rather than leaving nothing to pick from
+            None if self.labels().is_empty() => {
+                self.typed.pop();
+                HintInput::None
+            }
+            None => HintInput::None,
+        }
+    }
+
+    /// Where each label still matching the typed text goes, with the part left to type
+    pub fn labels(&self) -> Vec<(Point, String)> {
+        self.hints.iter()
+            .filter_map(|hint| Some((hint.range.start, hint.label.strip_prefix(self.typed.as_str())?.to_string())))
+            .collect()
+    }
+}
+
+/// `count` labels of equal length so none of them is a prefix of another
+fn labels(count: usize) -> Vec<String> {
+    let chars: Vec<char> = LABEL_CHARS.chars().collect();
+    let mut len = 1;
+    while chars.len().pow(len) < count {
+        len += 1;
+    }
+    (0..count)
+        .map(|mut i| {
+            let mut label = vec![' '; len as usize];
+            for slot in label.iter_mut().rev() {
+                *slot = chars[i % chars.len()];
+                i /= chars.len();
+            }
+            label.into_iter().collect()
+        })
+        .collect()
+}
@@ -0,0 +1,145 @@
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::process::Command;
+
+/// Identifies a hyperlink in `Hyperlinks`, cells store this instead of the whole URI. Ids are
+/// never 0 and fit in 24 bits, so a cell can keep one next to its flags.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct HyperlinkId(NonZeroU32);
+
+impl HyperlinkId {
+    /// Most hyperlinks that can be told apart
+    pub const MAX: u32 = (1 << 24) - 1;
+
+    pub(crate) fn from_bits(bits: u32) -> Option<Self> {
+        NonZeroU32::new(bits).filter(|bits| bits.get() <= Self::MAX).map(Self)
+    }
+
+    pub(crate) fn bits(self) -> u32 {
+        self.0.get()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Hyperlink {
+    pub uri: String,
+    /// The `id` parameter, cells with the same id and URI are one link even if they aren't next
+    /// to each other, like a link split over two panes in tmux
+    pub id: Option<String>,
+}
+
+/// Fewest links in use before `Hyperlinks::should_collect` asks for unused ones to be freed
+const MIN_COLLECT: usize = 1024;
+
+/// Every hyperlink the program has printed that may still be on the screen or in history
+#[derive(Default)]
+pub struct Hyperlinks {
+    // Indexed by id - 1, `None` where the id was freed
+    links: Vec<Option<Hyperlink>>,
+    ids: HashMap<Hyperlink, HyperlinkId>,
+    // Freed ids, handed out again before new ones
+    free: Vec<HyperlinkId>,
+    // Links in use that make the next collection worthwhile
+    collect_at: usize,
+}
+
+impl Hyperlinks {
+    /// Returns the id for `link`. Links without an `id` parameter get a new one each time, so
+    /// two separate links to the same URI are highlighted separately. `None` while there are
+    /// `HyperlinkId::MAX` links in use.
+    pub fn intern(&mut self, link: Hyperlink) -> Option<HyperlinkId> {
+        if let Some(id) = self.ids.get(&link) {
+            return Some(*id);
+        }
+        let id = match self.free.pop().or_else(|| HyperlinkId::from_bits(self.links.len() as u32 + 1)) {
+            Some(id) => id,
+            None => {
+                eprintln!("Warning: Too many hyperlinks, not linking {}", link.uri);
+                return None;
+            }
+        };
+        if link.id.is_some() {
+            self.ids.insert(link.clone(), id);
+        }
+        let index = id.bits() as usize - 1;
+        if index == self.links.len() {
+            self.links.push(Some(link));
+        } else {
+            self.links[index] = Some(link);
+        }
+        Some(id)
+    }
+
+    pub fn get(&self, id: HyperlinkId) -> Option<&Hyperlink> {
+        self.links.get(id.bits() as usize - 1)?.as_ref()
+    }
+
+    /// Number of links in use
+    pub fn count(&self) -> usize {
+        self.links.len() - self.free.len()
+    }
+
+    /// Whether enough links were added since the last `retain` that it's worth looking for
+    /// ones no cell uses anymore
+    pub fn should_collect(&self) -> bool {
+        self.count() >= self.collect_at.max(MIN_COLLECT)
+    }
+
+    /// Frees every link that isn't in `used`, their ids are reused by later links
+    pub fn retain(&mut self, used: &HashSet<HyperlinkId>) {
+        for (i, slot) in self.links.iter_mut().enumerate() {
+            let id = match HyperlinkId::from_bits(i as u32 + 1) {
+                Some(id) if slot.is_some() && !used.contains(&id) => id,
+                _ => continue,
+            };
+            if let Some(link) = slot.take().filter(|link| link.id.is_some()) {
+                self.ids.remove(&link);
+            }
+            self.free.push(id);
+        }
+        self.collect_at = 2 * self.count();
+    }
+}
+
+/// Parses the body of an OSC 8 sequence, `8;params;URI`. Returns `Some(None)` for the sequence
+/// that ends a link, which has an empty URI.
+pub fn parse_osc8(s: &str) -> Option<Option<Hyperlink>> {
+    let (params, uri) = s.strip_prefix("8;")?.split_once(';')?;
+    if uri.is_empty() {
+        return Some(None);
+    }
+    // Parameters are `key=value` pairs separated by colons, only `id` is defined
+    let id = params.split(':')
+        .find_map(|param| param.strip_prefix("id="))
+        .filter(|id| !id.is_empty())
+        .map(String::from);
+    Some(Some(Hyperlink { uri: uri.to_string(), id }))
+}
+
+/// Opens `uri` with `opener`, a command with optional arguments like `xdg-open` or
+/// `firefox --new-tab`
+pub fn open(opener: &str, uri: &str) {
+    let args = opener.split_whitespace().chain(Some(uri)).map(String::from).collect::<Vec<_>>();
+    spawn(&args, None);
+}
+
+/// Runs a command without waiting for it, in `cwd` if given
+pub fn spawn(args: &[String], cwd: Option<&Path>) {
+    let (program, args) = match args.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let mut command = Command::new(program);
+    command.args(args);
+    if let Some(cwd) = cwd {
+        command.current_dir(cwd);
+    }
+    match command.spawn() {
+        // Reap it in the background so it doesn't linger as a zombie
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => eprintln!("Warning: Could not run {program:?}: {e}"),
+    }
+}
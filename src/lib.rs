@@ -0,0 +1,15 @@
+//! The terminal emulator without a front end: escape sequence handling, the screen and
+//! scrollback, hyperlinks, the pty and deciding what to draw. None of it needs a display, the SDL
+//! window is only built with the `sdl-frontend` feature.
+
+pub mod color;
+pub mod grid;
+pub mod hyperlink;
+pub mod render;
+pub mod selection;
+pub mod term;
+pub mod tty;
+
+/// The escape sequence parser `term::Terminal` plugs into, driving a terminal takes
+/// `rust_ansi::term::Term`
+pub use rust_ansi;
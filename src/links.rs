@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+use regex::Regex;
+use crate::config::Config;
+use rust_terminal::hyperlink;
+use rust_terminal::selection::{logical_lines, Point, SelectionRange};
+use rust_terminal::term::CharacterCellManager;
+
+/// URLs, `trim_url` drops any punctuation caught at the end
+pub const URL_PATTERN: &str = r#"(?:(?:https?|ftp|file)://|mailto:)[^\s<>"'`]+"#;
+/// A file name with an extension, optionally with directories before it and `:line:col` after
+/// it like compiler errors print them. The path is the first group.
+pub const PATH_PATTERN: &str = r#"(?:^|[\s'"(\[<=])((?:~|\.{1,2})?/?(?:[\w.+-]+/)*[\w+-][\w.+-]*\.[A-Za-z]\w*(?::\d+(?::\d+)?)?)"#;
+
+pub enum LinkTarget {
+    Url(String),
+    /// An existing file, with the line and column that followed it if there were any
+    File { path: PathBuf, line: Option<usize>, col: Option<usize> },
+}
+
+/// A URL or file path found in the text. Programs can mark links explicitly with OSC 8, this
+/// is for everything else.
+pub struct DetectedLink {
+    pub range: SelectionRange,
+    /// The text as printed
+    pub text: String,
+    pub target: LinkTarget,
+}
+
+impl LinkTarget {
+    /// Works out what `text` points at, a URL or an existing file with an optional `:line:col`.
+    /// Relative paths are looked up in `cwd`.
+    pub fn from_text(text: &str, cwd: Option<&Path>) -> Option<Self> {
+        if text.contains("://") || text.starts_with("mailto:") {
+            return Some(LinkTarget::Url(text.to_string()));
+        }
+        let mut parts = text.splitn(3, ':');
+        let file = parts.next()?;
+        let line = parts.next().and_then(|n| n.parse().ok());
+        let col = parts.next().and_then(|n| n.parse().ok());
+        let path = resolve(file, cwd)?;
+        if !path.exists() {
+            return None;
+        }
+        Some(LinkTarget::File { path, line, col })
+    }
+
+    /// Opens URLs with `link_opener` and files with the `file_opener` template
+    pub fn open(&self, config: &Config, cwd: Option<&Path>) {
+        match self {
+            LinkTarget::Url(url) => hyperlink::open(&config.link_opener, url),
+            LinkTarget::File { path, line, col } => {
+                let path = path.to_string_lossy();
+                let (line, col) = (line.unwrap_or(1).to_string(), col.unwrap_or(1).to_string());
+                let mut args = Vec::new();
+                let mut has_file = false;
+                for arg in config.file_opener.split_whitespace() {
+                    has_file |= arg.contains("{file}");
+                    args.push(arg.replace("{file}", &path).replace("{line}", &line).replace("{col}", &col));
+                }
+                if !has_file {
+                    args.push(path.into_owned());
+                }
+                hyperlink::spawn(&args, cwd);
+            }
+        }
+    }
+}
+
+pub struct LinkDetector {
+    url: Regex,
+    file: Regex,
+}
+
+impl Default for LinkDetector {
+    fn default() -> Self {
+        Self {
+            url: Regex::new(URL_PATTERN).unwrap(),
+            file: Regex::new(PATH_PATTERN).unwrap(),
+        }
+    }
+}
+
+impl LinkDetector {
+    /// Finds the link covering the absolute position `point`. The whole logical line is
+    /// searched so links broken over soft wraps are found. Relative paths are looked up in
+    /// `cwd` and only paths that exist count.
+    pub fn link_at(&self, cells: &CharacterCellManager, point: Point, cwd: Option<&Path>) -> Option<DetectedLink> {
+        let line = logical_lines(cells, point.line, point.line).into_iter().next()?;
+
+        for m in self.url.find_iter(&line.text) {
+            let url = trim_url(m.as_str());
+            match line.range(m.start(), m.start() + url.len()) {
+                Some(range) if range.contains(point) => {
+                    return Some(DetectedLink { range, text: url.to_string(), target: LinkTarget::Url(url.to_string()) });
+                }
+                _ => {}
+            }
+        }
+
+        for caps in self.file.captures_iter(&line.text) {
+            let file = caps.get(1)?;
+            match line.range(file.start(), file.end()) {
+                Some(range) if range.contains(point) => {
+                    let target = LinkTarget::from_text(file.as_str(), cwd)?;
+                    return Some(DetectedLink { range, text: file.as_str().to_string(), target });
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// Drops punctuation that more likely ends the sentence than the URL
+pub fn trim_url(mut url: &str) -> &str {
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ':', ';', '!', '?', '\'', '"']);
+        // Keep closing parens that belong to the URL, like in Wikipedia links
+        let trimmed = match trimmed.strip_suffix(')') {
+            Some(rest) if trimmed.matches('(').count() < trimmed.matches(')').count() => rest,
+            _ => trimmed,
+        };
+        if trimmed.len() == url.len() {
+            return url;
+        }
+        url = trimmed;
+    }
+}
+
+fn resolve(path: &str, cwd: Option<&Path>) -> Option<PathBuf> {
+    if let Some(rest) = path.strip_prefix("~/") {
+        return Some(PathBuf::from(std::env::var_os("HOME")?).join(rest));
+    }
+    let path = Path::new(path);
+    Some(match cwd {
+        Some(cwd) if path.is_relative() => cwd.join(path),
+        _ => path.to_path_buf(),
+    })
+}
@@ -1,22 +1,46 @@
+mod box_drawing;
+mod clipboard;
+mod config;
+mod hints;
+mod links;
+mod mouse;
 mod renderer;
+mod search;
 mod text_area;
-mod tty;
-mod term;
+mod vi_mode;
 
 extern crate sdl2;
 extern crate nix;
 extern crate libc;
 extern crate rust_ansi;
 
+use rust_terminal::{hyperlink, tty};
+
+use sdl2::clipboard::ClipboardUtil;
 use sdl2::pixels::Color;
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use std::time::Duration;
+use sdl2::render::WindowCanvas;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
+use std::time::{Duration, Instant};
 use rust_ansi::ansi_escaper;
 use rust_ansi::term::Term;
-use crate::renderer::TermRenderer;
-use crate::term::Terminal;
+use crate::config::Config;
+use crate::hints::{Hint, HintAction, HintInput, HintMode};
+use rust_terminal::hyperlink::HyperlinkId;
+use crate::links::{LinkDetector, LinkTarget};
+use crate::mouse::{MouseAction, MouseButton, MouseReporter};
+use crate::renderer::{Frame, TermRenderer};
+use rust_terminal::render::Overlays;
+use crate::search::{Search, SearchAction};
+use rust_terminal::selection::{Point, Selection, SelectionKind, SelectionRange};
+use crate::clipboard::Osc52Policy;
+use rust_terminal::term::{CharacterCellManager, Terminal, TerminalEvent};
 use crate::text_area::TextArea;
+use rust_terminal::tty::{ForkPTY, Utf8Decoder};
+use crate::vi_mode::{ViAction, ViMode};
+
+/// Sent by the PTY reader thread to wake the event loop when the program printed something
+struct PtyOutput;
 
 fn main() {
     main_new();
@@ -27,7 +51,7 @@ fn main_old() {
     let sdl_context = sdl2::init().unwrap();
     let ttf_context = sdl2::ttf::init().unwrap();
 
-    let ren = TermRenderer::new(&sdl_context, &ttf_context, 80, 24);
+    let ren = TermRenderer::new(&sdl_context, &ttf_context, &Config::default(), 80, 24);
     let char_surf = ren.font.render_char('i')
         .shaded(Color{r:0,g:0,b:0,a:0}, Color{r:0,g:0,b:0,a:0})
         .unwrap();
@@ -322,12 +346,13 @@ fn main_old() {
 }
 
 fn main_new() {
+    let config = Config::load();
     let width = 80;
     let height = 24;
     let con = tty::ForkPTY::new(width as u16, height as u16);
     let sdl_context = sdl2::init().unwrap();
     let ttf_context = sdl2::ttf::init().unwrap();
-    let mut renderer = TermRenderer::new(&sdl_context, &ttf_context, width, height);
+    let mut renderer = TermRenderer::new(&sdl_context, &ttf_context, &config, width, height);
 
     let window = renderer.video_subsystem.window("Terminal", renderer.font_width*(width as u32), renderer.font_height*(height as u32))
         .opengl()
@@ -335,6 +360,8 @@ fn main_new() {
         .unwrap();
 
     let mut canvas = window.into_canvas()
+        // The grid is drawn into a texture that is kept between frames
+        .target_texture()
         .build()
         .unwrap();
     std::thread::sleep(Duration::new(0, 10000000)); // Allow sdl to init before drawing anything
@@ -342,44 +369,215 @@ fn main_new() {
     canvas.set_draw_color(Color::RGB(0, 0, 0));
     canvas.clear();
     canvas.present();
+    let texture_creator = canvas.texture_creator();
+    let mut frame = Frame::new(&texture_creator);
 
     let mut event_pump = renderer.sdl_context.event_pump().unwrap();
+    let clipboard = renderer.video_subsystem.clipboard();
+    let mut mouse = MouseReporter::default();
+    let mut selection: Option<Selection> = None;
+    let mut selecting = false;
+    let mut search: Option<Search> = None;
+    let mut vi_mode: Option<ViMode> = None;
+    let mut pointer_in_window = false;
+    let link_detector = LinkDetector::default();
+    // Link found under the pointer while Ctrl is held, for the cell and `lines_scrolled` it was
+    // looked for at
+    let mut detected_cache: Option<((Point, usize), Option<SelectionRange>)> = None;
+    let mut hint_mode: Option<HintMode> = None;
+    // Title and icon name set by the program, and what the window shows
+    let (mut title, mut icon_name) = (String::new(), String::new());
+    let mut window_title = String::from("Terminal");
+    // When the window title has to be worked out again, and when it last was. The program's
+    // output can change the directory, so with `{cwd}` in the template that is checked again
+    // after output, but at most every `CWD_CHECK_INTERVAL`.
+    let cwd_in_title = config.title_template.contains("{cwd}");
+    let mut title_due = Some(Instant::now());
+    let mut title_updated = Instant::now();
 
-    let mut terminal = Term::new(Box::new(Terminal::new(width, height)));
+    let mut terminal = Term::new(Box::new(Terminal::with_history_limit(width, height, config.history_limit)));
+
+    let event_subsystem = renderer.sdl_context.event().unwrap();
+    event_subsystem.register_custom_event::<PtyOutput>().unwrap();
+    let event_sender = event_subsystem.event_sender();
+    let reader = con.spawn_reader(move || {
+        // Only fails once SDL is shutting down
+        let _ = event_sender.push_custom_event(PtyOutput);
+    });
+    let mut decoder = Utf8Decoder::default();
+    // The event that ended the wait at the end of the last frame
+    let mut woken_by: Option<Event> = None;
+    let refresh_rate = canvas.window().display_mode().map_or(0, |mode| mode.refresh_rate);
+    let frame_interval = Duration::from_secs(1) / if refresh_rate > 0 { refresh_rate as u32 } else { 60 };
+    let mut next_frame = Instant::now();
+    // When the program started the synchronized update that is going on
+    let mut sync_since: Option<Instant> = None;
 
     'running: loop {
-        let conres = con.read();
-        match conres {
-            Ok(_) => {}
-            Err(_) => {
+        let output = match reader.take() {
+            Some(output) => output,
+            None => {
                 println!("Child process exited!");
                 break 'running;
             }
-        }
-        let conres = conres.unwrap();
+        };
 
-        if conres.1 > 0 {
-            let mut res_str = String::new();
-            for i in 0..conres.1 {
-                res_str.push(char::from(conres.0[i]))
-            }
-            println!("Read: {:?}", res_str);
+        if !output.is_empty() {
+            let res_str = decoder.decode(&output);
+            let lines_scrolled = terminal.framebuffer().lines_scrolled;
             terminal.write(res_str);
+            if !terminal.framebuffer().modes.synchronized_output {
+                sync_since = None;
+            } else if sync_since.is_none() {
+                sync_since = Some(Instant::now());
+            }
+            // Copy mode keeps the view where it is like tmux does
+            if config.scroll_to_bottom_on_output && vi_mode.is_none() {
+                renderer.scroll_to_bottom();
+            } else if renderer.display_offset > 0 {
+                // Keep showing the same lines while new output pushes them up
+                let new_lines = terminal.framebuffer().lines_scrolled - lines_scrolled;
+                renderer.scroll_viewport(terminal.framebuffer(), new_lines as isize);
+            }
+            if let Some(search) = search.as_mut() {
+                search.update(terminal.framebuffer());
+            }
+            for event in terminal.framebuffer().take_events() {
+                match event {
+                    TerminalEvent::ClipboardStore(data) if config.osc52 != Osc52Policy::Deny => {
+                        clipboard::osc52_store(&clipboard, &data, config.osc52_max_bytes);
+                    }
+                    TerminalEvent::ClipboardLoad if config.osc52 == Osc52Policy::ReadWrite => {
+                        clipboard::osc52_load(&clipboard, &con, config.osc52_max_bytes);
+                    }
+                    TerminalEvent::ClipboardStore(_) | TerminalEvent::ClipboardLoad => {
+                        eprintln!("Warning: OSC 52 clipboard access denied by the config");
+                    }
+                    TerminalEvent::Title { title: new_title, icon_name: new_icon_name } => {
+                        title = new_title;
+                        icon_name = new_icon_name;
+                        title_due = Some(Instant::now());
+                    }
+                    TerminalEvent::Reply(reply) => {
+                        con.write(reply);
+                    }
+                }
+            }
+            if cwd_in_title && title_due.is_none() {
+                title_due = Some(title_updated + CWD_CHECK_INTERVAL);
+            }
+        }
+        if title_due.is_some_and(|due| due <= Instant::now()) {
+            title_due = None;
+            title_updated = Instant::now();
+            let new_window_title = format_title(&config.title_template, &title, &icon_name, &con);
+            if new_window_title != window_title {
+                if let Err(e) = canvas.window_mut().set_title(&new_window_title) {
+                    eprintln!("Warning: Could not set the window title: {e}");
+                }
+                window_title = new_window_title;
+            }
         }
 
-        for event in event_pump.poll_iter() {
+        for event in woken_by.take().into_iter().chain(event_pump.poll_iter()) {
             match event {
                 Event::Quit {..}  => {
                     break 'running
                 },
+                Event::Window { win_event: WindowEvent::FocusGained, .. } => renderer.focused = true,
+                Event::Window { win_event: WindowEvent::FocusLost, .. } => renderer.focused = false,
+                Event::Window { win_event: WindowEvent::Enter, .. } => pointer_in_window = true,
+                Event::Window { win_event: WindowEvent::Leave, .. } => pointer_in_window = false,
                 Event::TextInput { text, .. } => {
+                    renderer.reset_cursor_blink();
+                    if let Some(hints) = hint_mode.as_mut() {
+                        for ch in text.chars() {
+                            if let HintInput::Selected(hint) = hints.handle_char(ch) {
+                                run_hint(hint, &config, &clipboard, &con);
+                                hint_mode = None;
+                                break;
+                            }
+                        }
+                        continue;
+                    }
+                    if let Some(s) = search.as_mut().filter(|s| s.active) {
+                        s.query.push_str(&text);
+                        update_search(s, vi_mode.as_mut(), &mut renderer, terminal.framebuffer());
+                        continue;
+                    }
+                    if vi_mode.is_some() {
+                        for ch in text.chars() {
+                            let action = match vi_mode.as_mut() {
+                                Some(vi) => vi.handle_char(ch, terminal.framebuffer()),
+                                None => break,
+                            };
+                            handle_vi_action(action, &mut vi_mode, &mut search, &clipboard, &mut renderer, terminal.framebuffer());
+                        }
+                        continue;
+                    }
+                    renderer.scroll_to_bottom();
                     con.write(text);
                 }
-                Event::KeyDown { keycode, .. } => {
+                Event::KeyDown { keycode, keymod, .. } => {
                     if keycode.clone().is_none() {
                         continue;
                     }
+                    renderer.reset_cursor_blink();
+                    let ctrl = keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD);
+                    let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+                    let bracketed_paste = terminal.framebuffer().modes.bracketed_paste;
+                    if let Some(hints) = hint_mode.as_mut() {
+                        if let HintInput::Exit = hints.handle_key(keycode.unwrap()) {
+                            hint_mode = None;
+                        }
+                        continue;
+                    }
+                    if let Some(s) = search.as_mut().filter(|s| s.active) {
+                        // Keys drive the search prompt instead of the program while it's open
+                        match s.handle_key(keycode.unwrap(), shift, ctrl) {
+                            SearchAction::None => {}
+                            SearchAction::Changed => update_search(s, vi_mode.as_mut(), &mut renderer, terminal.framebuffer()),
+                            // Copy mode is already on the match, Enter just closes the prompt
+                            SearchAction::Jump { .. } if vi_mode.is_some() => s.active = false,
+                            SearchAction::Jump { backwards } => jump_to_match(s, backwards, None, &mut renderer, terminal.framebuffer()),
+                            SearchAction::Close => search = None,
+                        }
+                        continue;
+                    }
+                    if let Some(vi) = vi_mode.as_mut() {
+                        let action = vi.handle_key(keycode.unwrap(), ctrl, terminal.framebuffer());
+                        handle_vi_action(action, &mut vi_mode, &mut search, &clipboard, &mut renderer, terminal.framebuffer());
+                        continue;
+                    }
                     match keycode.clone().unwrap() {
+                        Keycode::F if ctrl && shift => search = Some(Search::new()),
+                        Keycode::H if ctrl && shift => {
+                            let cells = terminal.framebuffer();
+                            let top = renderer.top_line(cells);
+                            let cwd = con.cwd();
+                            // Plain numbers aren't hashes, the regex crate has no lookahead to rule them out
+                            let hints = HintMode::new(&config.hints, cells, top, top + renderer.height() - 1, |rule, text| {
+                                (rule.name != "hash" || text.bytes().any(|b| b.is_ascii_alphabetic()))
+                                    && (rule.action != HintAction::Open || LinkTarget::from_text(text, cwd.as_deref()).is_some())
+                            });
+                            if !hints.is_empty() {
+                                hint_mode = Some(hints);
+                            }
+                        }
+                        Keycode::Space if ctrl && shift => {
+                            let cursor = terminal.framebuffer().cursor();
+                            vi_mode = Some(ViMode::new(Point::new(terminal.framebuffer().lines_scrolled + cursor.y, cursor.x)));
+                            selection = None;
+                            selecting = false;
+                        }
+                        Keycode::V if ctrl && shift => clipboard::paste(&clipboard, &con, bracketed_paste),
+                        Keycode::Equals | Keycode::Plus | Keycode::KpPlus if ctrl => {
+                            zoom(renderer.font_size + 1, &config, &mut renderer, &mut canvas, &terminal, &con);
+                        }
+                        Keycode::Minus | Keycode::KpMinus if ctrl => {
+                            zoom(renderer.font_size.saturating_sub(1).max(MIN_FONT_SIZE), &config, &mut renderer, &mut canvas, &terminal, &con);
+                        }
+                        Keycode::Num0 | Keycode::Kp0 if ctrl => zoom(config.font_size, &config, &mut renderer, &mut canvas, &terminal, &con),
                         Keycode::Backspace => {con.write(String::from('\x08'));}
                         Keycode::Tab => {con.write(String::from('\t'));}
                         Keycode::Return => {con.write(String::from('\n'));}
@@ -440,10 +638,15 @@ fn main_new() {
                         Keycode::PrintScreen => {}
                         Keycode::ScrollLock => {}
                         Keycode::Pause => {}
+                        Keycode::Insert if shift => clipboard::paste(&clipboard, &con, bracketed_paste),
                         Keycode::Insert => {}
+                        Keycode::Home if shift => renderer.scroll_to_top(terminal.framebuffer()),
                         Keycode::Home => {}
+                        Keycode::PageUp if shift => renderer.scroll_viewport(terminal.framebuffer(), renderer.height() as isize),
                         Keycode::PageUp => {}
+                        Keycode::End if shift => renderer.scroll_to_bottom(),
                         Keycode::End => {}
+                        Keycode::PageDown if shift => renderer.scroll_viewport(terminal.framebuffer(), -(renderer.height() as isize)),
                         Keycode::PageDown => {}
                         Keycode::Right => {con.write(String::from("\x1B[C"));}
                         Keycode::Left => {con.write(String::from("\x1B[D"));}
@@ -490,7 +693,7 @@ fn main_new() {
                         Keycode::Undo => {}
                         Keycode::Cut => {}
                         Keycode::Copy => {}
-                        Keycode::Paste => {}
+                        Keycode::Paste => clipboard::paste(&clipboard, &con, bracketed_paste),
                         Keycode::Find => {}
                         Keycode::Mute => {}
                         Keycode::VolumeUp => {}
@@ -596,12 +799,158 @@ fn main_new() {
 
                     //ta.print_char(ch.to_ascii_lowercase());
                 },
+                Event::MouseButtonDown { mouse_btn, clicks, x, y, .. } => {
+                    let keymod = renderer.sdl_context.keyboard().mod_state();
+                    let modes = terminal.framebuffer().modes;
+                    let cell = renderer.cell_at(x, y);
+                    let ctrl = keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD);
+                    let link = hyperlink_at(&renderer, terminal.framebuffer(), cell)
+                        .and_then(|id| terminal.framebuffer().hyperlinks.get(id))
+                        .filter(|_| ctrl);
+                    let point = Point::new(renderer.top_line(terminal.framebuffer()) + cell.1, cell.0);
+                    // The shell's directory is only looked up when a path could be opened
+                    let (cwd, detected) = if ctrl && link.is_none() {
+                        let cwd = con.cwd();
+                        let detected = link_detector.link_at(terminal.framebuffer(), point, cwd.as_deref());
+                        (cwd, detected)
+                    } else {
+                        (None, None)
+                    };
+                    // Ctrl+click opens a link, Ctrl+right click copies it
+                    if let Some(link) = link {
+                        match mouse_btn {
+                            sdl2::mouse::MouseButton::Left => hyperlink::open(&config.link_opener, &link.uri),
+                            sdl2::mouse::MouseButton::Right => clipboard::copy(&clipboard, &link.uri),
+                            _ => {}
+                        }
+                    } else if let Some(detected) = detected {
+                        match mouse_btn {
+                            sdl2::mouse::MouseButton::Left => detected.target.open(&config, cwd.as_deref()),
+                            sdl2::mouse::MouseButton::Right => clipboard::copy(&clipboard, &detected.text),
+                            _ => {}
+                        }
+                    } else if vi_mode.is_some() {
+                        // Copy mode has its own selection, the mouse neither reports to the
+                        // program nor starts one that wouldn't be shown
+                    } else if mouse::is_reporting(&modes, keymod) {
+                        if let Some(button) = MouseButton::from_sdl(mouse_btn) {
+                            mouse.report(&con, &modes, Some(button), MouseAction::Press, cell, keymod);
+                        }
+                    } else if mouse_btn == sdl2::mouse::MouseButton::Left {
+                        let kind = match clicks {
+                            1 if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => SelectionKind::Block,
+                            1 => SelectionKind::Simple,
+                            2 => SelectionKind::Word,
+                            _ => SelectionKind::Line,
+                        };
+                        selection = Some(Selection::new(kind, point));
+                        selecting = true;
+                    }
+                }
+                Event::MouseButtonUp { mouse_btn, x, y, .. } => {
+                    if selecting && mouse_btn == sdl2::mouse::MouseButton::Left {
+                        selecting = false;
+                        match &selection {
+                            Some(sel) if !sel.is_empty() => clipboard::copy(&clipboard, &sel.text(terminal.framebuffer())),
+                            _ => selection = None,
+                        }
+                    } else if let Some(button) = MouseButton::from_sdl(mouse_btn).filter(|_| vi_mode.is_none()) {
+                        let keymod = renderer.sdl_context.keyboard().mod_state();
+                        mouse.report(&con, &terminal.framebuffer().modes, Some(button), MouseAction::Release, renderer.cell_at(x, y), keymod);
+                    }
+                }
+                Event::MouseMotion { mousestate, x, y, .. } => {
+                    mouse.position = (x, y);
+                    pointer_in_window = true;
+                    let cell = renderer.cell_at(x, y);
+                    if selecting {
+                        if let Some(sel) = selection.as_mut() {
+                            sel.update(Point::new(renderer.top_line(terminal.framebuffer()) + cell.1, cell.0));
+                        }
+                    } else if vi_mode.is_none() {
+                        let keymod = renderer.sdl_context.keyboard().mod_state();
+                        mouse.report(&con, &terminal.framebuffer().modes, MouseButton::from_state(&mousestate), MouseAction::Motion, cell, keymod);
+                    }
+                }
+                Event::MouseWheel { y, .. } => {
+                    let keymod = renderer.sdl_context.keyboard().mod_state();
+                    let modes = terminal.framebuffer().modes;
+                    if vi_mode.is_none() && mouse::is_reporting(&modes, keymod) {
+                        let button = if y > 0 { MouseButton::WheelUp } else { MouseButton::WheelDown };
+                        let cell = renderer.cell_at(mouse.position.0, mouse.position.1);
+                        for _ in 0..y.abs() {
+                            mouse.report(&con, &modes, Some(button), MouseAction::Press, cell, keymod);
+                        }
+                    } else {
+                        renderer.scroll_viewport(terminal.framebuffer(), y as isize * config.wheel_scroll_lines as isize);
+                    }
+                }
                 _e => {
                     //println!("{:?}", _e)
                 }
             }
         }
-        renderer.render(terminal.framebuffer(), &mut canvas);
+        // Output keeps being parsed as it comes, but the screen is drawn at most once per display
+        // frame, and not while the program is in the middle of a synchronized update
+        let now = Instant::now();
+        let draw_at = match sync_since {
+            Some(since) => next_frame.max(since + SYNC_TIMEOUT),
+            None => next_frame,
+        };
+        if now < draw_at {
+            // Output `take` left behind is parsed right away, nothing would wake us for it
+            woken_by = if reader.has_more() {
+                event_pump.poll_event()
+            } else {
+                event_pump.wait_event_timeout((draw_at - now).as_millis() as u32 + 1)
+            };
+            continue;
+        }
+        next_frame = now + frame_interval;
+
+        let top_line = renderer.top_line(terminal.framebuffer());
+        let shown_selection = match &vi_mode {
+            Some(vi) => vi.selection.as_ref(),
+            None => selection.as_ref(),
+        };
+        let hovered_cell = renderer.cell_at(mouse.position.0, mouse.position.1);
+        let ctrl = renderer.sdl_context.keyboard().mod_state().intersects(Mod::LCTRLMOD | Mod::RCTRLMOD);
+        let hovered_link = if pointer_in_window {
+            hyperlink_at(&renderer, terminal.framebuffer(), hovered_cell)
+        } else {
+            None
+        };
+        // Only looked for while Ctrl is held, and again once the pointer moves to another cell or
+        // the screen changes, since paths need the shell's directory to be checked. A link can
+        // span every row of a wrapped line, so any damaged row counts.
+        let detected_link = if pointer_in_window && ctrl && hovered_link.is_none() {
+            let cells = terminal.framebuffer();
+            let point = Point::new(top_line + hovered_cell.1, hovered_cell.0);
+            let key = (point, cells.lines_scrolled);
+            let damaged = cells.damage.rows.iter().any(Option::is_some);
+            match detected_cache {
+                Some((cached, range)) if cached == key && !damaged => range,
+                _ => {
+                    let range = link_detector.link_at(cells, point, con.cwd().as_deref()).map(|link| link.range);
+                    detected_cache = Some((key, range));
+                    range
+                }
+            }
+        } else {
+            detected_cache = None;
+            None
+        };
+        let overlays = Overlays {
+            selection: shown_selection.map(|sel| sel.range(terminal.framebuffer())),
+            matches: search.as_ref().map_or(Vec::new(), |s| s.visible_matches(top_line, top_line + renderer.height() - 1)),
+            current_match: search.as_ref().and_then(|s| s.current_match()),
+            status_line: search.as_ref().filter(|s| s.active).map(|s| s.status_line()),
+            vi_cursor: vi_mode.as_ref().map(|vi| vi.cursor),
+            hovered_link,
+            detected_link,
+            hint_labels: hint_mode.as_ref().map_or(Vec::new(), |hints| hints.labels()),
+        };
+        renderer.render(terminal.framebuffer(), &overlays, &mut frame, &mut canvas);
         terminal.completed_render();
 
         for sc in event_pump.keyboard_state().pressed_scancodes() {
@@ -610,6 +959,140 @@ fn main_new() {
             }
         }
         canvas.present();
-        //std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
+
+        // Sleep until there is input or output, the cursor has to blink or the title is due
+        let title_timeout = title_due.map(|due| due.saturating_duration_since(Instant::now()));
+        let output_waiting = Some(Duration::ZERO).filter(|_| reader.has_more());
+        let timeout = renderer.next_blink(terminal.framebuffer().cursor().blinking).into_iter()
+            .chain(title_timeout)
+            .chain(output_waiting)
+            .min();
+        woken_by = match timeout {
+            Some(timeout) => event_pump.wait_event_timeout(timeout.as_millis() as u32 + 1),
+            None => Some(event_pump.wait_event()),
+        };
+    }
+}
+
+/// Fills in the `title_template` placeholders, falling back to "Terminal" if nothing is left
+fn format_title(template: &str, title: &str, icon_name: &str, con: &ForkPTY) -> String {
+    let mut res = template.replace("{title}", title).replace("{icon}", icon_name);
+    if res.contains("{cwd}") {
+        let cwd = con.cwd().map(|cwd| cwd.to_string_lossy().into_owned()).unwrap_or_default();
+        // Shortened like shells do
+        let cwd = match std::env::var("HOME") {
+            Ok(home) if !home.is_empty() && (cwd == home || cwd.starts_with(&format!("{home}/"))) => format!("~{}", &cwd[home.len()..]),
+            _ => cwd,
+        };
+        res = res.replace("{cwd}", &cwd);
+    }
+    match res.trim() {
+        "" => String::from("Terminal"),
+        _ => res,
+    }
+}
+
+fn run_hint(hint: Hint, config: &Config, clipboard: &ClipboardUtil, con: &ForkPTY) {
+    match hint.action {
+        HintAction::Copy => clipboard::copy(clipboard, &hint.text),
+        HintAction::Paste => {
+            con.write(hint.text);
+        }
+        HintAction::Open => {
+            let cwd = con.cwd();
+            if let Some(target) = LinkTarget::from_text(&hint.text, cwd.as_deref()) {
+                target.open(config, cwd.as_deref());
+            }
+        }
+    }
+}
+
+/// The hyperlink in the cell at the 0-based column and row of the view
+fn hyperlink_at(renderer: &TermRenderer, cells: &CharacterCellManager, (col, row): (usize, usize)) -> Option<HyperlinkId> {
+    cells.line(renderer.top_line(cells) + row)?.get(col)?.hyperlink()
+}
+
+/// Longest a synchronized update (mode 2026) can hold back drawing, in case the program never
+/// ends it
+const SYNC_TIMEOUT: Duration = Duration::from_millis(150);
+
+/// Least time between checks of the shell's directory for the window title
+const CWD_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Smallest font size zooming out goes to
+const MIN_FONT_SIZE: u16 = 4;
+
+/// Reloads the fonts at `size` points. Depending on the config either the window is resized to
+/// keep the grid, or the grid is refitted to the window and the program is told the new size.
+fn zoom(size: u16, config: &Config, renderer: &mut TermRenderer, canvas: &mut WindowCanvas, terminal: &Term<CharacterCellManager>, con: &ForkPTY) {
+    if size == renderer.font_size {
+        return;
+    }
+    if let Err(e) = renderer.set_font_size(size) {
+        eprintln!("Warning: Could not load fonts at size {size}: {e}");
+        return;
+    }
+    if config.zoom_resizes_window {
+        let (width, height) = (renderer.width() as u32, renderer.height() as u32);
+        if let Err(e) = canvas.window_mut().set_size(renderer.font_width * width, renderer.font_height * height) {
+            eprintln!("Warning: Could not resize the window: {e}");
+        }
+        return;
+    }
+    let (window_width, window_height) = canvas.window().size();
+    let width = ((window_width / renderer.font_width) as usize).max(1);
+    let height = ((window_height / renderer.font_height) as usize).max(1);
+    renderer.resize(width, height);
+    terminal.framebuffer().request_resize(width, height);
+    con.resize(width as u16, height as u16);
+}
+
+/// Reruns the search after the query changed and jumps to the closest match, searching from
+/// the bottom of the view or from the copy mode cursor
+fn update_search(search: &mut Search, vi_mode: Option<&mut ViMode>, renderer: &mut TermRenderer, cells: &CharacterCellManager) {
+    let backwards = vi_mode.as_ref().is_none_or(|vi| vi.search_backwards);
+    search.restart(cells);
+    jump_to_match(search, backwards, vi_mode, renderer, cells);
+}
+
+/// Scrolls to the next match. In copy mode the cursor moves onto it.
+fn jump_to_match(search: &mut Search, backwards: bool, vi_mode: Option<&mut ViMode>, renderer: &mut TermRenderer, cells: &CharacterCellManager) {
+    let (top, bottom) = match &vi_mode {
+        Some(vi) => (vi.cursor.line, vi.cursor.line),
+        None => {
+            let top = renderer.top_line(cells);
+            (top, top + cells.grid.height() - 1)
+        }
+    };
+    if let Some(m) = search.jump(backwards, top, bottom) {
+        renderer.scroll_to_line(cells, m.start.line);
+        if let Some(vi) = vi_mode {
+            vi.set_cursor(m.start);
+        }
+    }
+}
+
+fn handle_vi_action(action: ViAction, vi_mode: &mut Option<ViMode>, search: &mut Option<Search>, clipboard: &ClipboardUtil, renderer: &mut TermRenderer, cells: &CharacterCellManager) {
+    match action {
+        ViAction::None => {}
+        ViAction::Yank(text) => {
+            clipboard::copy(clipboard, &text);
+            *vi_mode = None;
+            *search = None;
+        }
+        ViAction::Search => *search = Some(Search::new()),
+        ViAction::SearchNext { reverse } => {
+            if let (Some(s), Some(vi)) = (search.as_mut(), vi_mode.as_mut()) {
+                let backwards = vi.search_backwards != reverse;
+                jump_to_match(s, backwards, Some(vi), renderer, cells);
+            }
+        }
+        ViAction::Exit => {
+            *vi_mode = None;
+            *search = None;
+        }
+    }
+    if let Some(vi) = vi_mode {
+        renderer.reveal_line(cells, vi.cursor.line);
     }
 }
@@ -0,0 +1,137 @@
+use sdl2::keyboard::Mod;
+use sdl2::mouse::MouseState;
+use rust_terminal::term::{MouseEncoding, MouseTracking, TerminalModes};
+use rust_terminal::tty::ForkPTY;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    WheelUp,
+    WheelDown,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum MouseAction {
+    Press,
+    Release,
+    Motion,
+}
+
+impl MouseButton {
+    pub fn from_sdl(button: sdl2::mouse::MouseButton) -> Option<Self> {
+        match button {
+            sdl2::mouse::MouseButton::Left => Some(MouseButton::Left),
+            sdl2::mouse::MouseButton::Middle => Some(MouseButton::Middle),
+            sdl2::mouse::MouseButton::Right => Some(MouseButton::Right),
+            _ => None,
+        }
+    }
+
+    /// The button held down during a motion event, if any
+    pub fn from_state(state: &MouseState) -> Option<Self> {
+        if state.left() {
+            Some(MouseButton::Left)
+        } else if state.middle() {
+            Some(MouseButton::Middle)
+        } else if state.right() {
+            Some(MouseButton::Right)
+        } else {
+            None
+        }
+    }
+
+    fn code(&self) -> usize {
+        match self {
+            MouseButton::Left => 0,
+            MouseButton::Middle => 1,
+            MouseButton::Right => 2,
+            MouseButton::WheelUp => 64,
+            MouseButton::WheelDown => 65,
+        }
+    }
+}
+
+/// Whether mouse events go to the application instead of being used for local selection.
+/// Holding shift always bypasses reporting.
+pub fn is_reporting(modes: &TerminalModes, keymod: Mod) -> bool {
+    modes.mouse_tracking != MouseTracking::Off && !keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD)
+}
+
+/// Builds the report for a mouse event on the 0-based cell `(col, row)`, or `None` if the
+/// current tracking mode doesn't want it.
+pub fn encode_report(modes: &TerminalModes, button: Option<MouseButton>, action: MouseAction, col: usize, row: usize, keymod: Mod) -> Option<Vec<u8>> {
+    if !is_reporting(modes, keymod) {
+        return None;
+    }
+    let reported = match (modes.mouse_tracking, action) {
+        (MouseTracking::Off, _) => false,
+        (MouseTracking::X10, MouseAction::Press) => true,
+        (MouseTracking::X10, _) => false,
+        (_, MouseAction::Press) | (_, MouseAction::Release) => true,
+        (MouseTracking::Normal, MouseAction::Motion) => false,
+        (MouseTracking::ButtonEvent, MouseAction::Motion) => button.is_some(),
+        (MouseTracking::AnyEvent, MouseAction::Motion) => true,
+    };
+    let is_wheel = matches!(button, Some(MouseButton::WheelUp) | Some(MouseButton::WheelDown));
+    if !reported || (is_wheel && action == MouseAction::Release) {
+        return None;
+    }
+
+    // Motion without a button is reported as a release with the motion bit set
+    let mut code = button.map_or(3, |b| b.code());
+    if action == MouseAction::Motion {
+        code += 32;
+    }
+    if modes.mouse_tracking != MouseTracking::X10 {
+        if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) {
+            code += 8;
+        }
+        if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) {
+            code += 16;
+        }
+    }
+    let (x, y) = (col + 1, row + 1);
+
+    if modes.mouse_encoding == MouseEncoding::Sgr {
+        let fin = if action == MouseAction::Release { 'm' } else { 'M' };
+        return Some(format!("\x1b[<{code};{x};{y}{fin}").into_bytes());
+    }
+    // The older encodings can't say which button was released
+    if action == MouseAction::Release {
+        code = (code & !0b11) | 3;
+    }
+    match modes.mouse_encoding {
+        MouseEncoding::Urxvt => Some(format!("\x1b[{};{x};{y}M", code + 32).into_bytes()),
+        _ => {
+            // Each value is sent as a single byte offset by 32
+            if x > 223 || y > 223 {
+                return None;
+            }
+            Some(vec![0x1b, b'[', b'M', (code + 32) as u8, (x + 32) as u8, (y + 32) as u8])
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct MouseReporter {
+    /// Last known pointer position in window coordinates, wheel events don't carry one
+    pub position: (i32, i32),
+    last_cell: Option<(usize, usize)>,
+}
+
+impl MouseReporter {
+    pub fn report(&mut self, con: &ForkPTY, modes: &TerminalModes, button: Option<MouseButton>, action: MouseAction, cell: (usize, usize), keymod: Mod) {
+        // Motion is only interesting once it crosses into another cell
+        if action == MouseAction::Motion && self.last_cell == Some(cell) {
+            return;
+        }
+        self.last_cell = Some(cell);
+        if let Some(report) = encode_report(modes, button, action, cell.0, cell.1, keymod) {
+            if let Err(e) = con.write_bytes(&report) {
+                eprintln!("Warning: Could not report the mouse: {e}");
+            }
+        }
+    }
+}
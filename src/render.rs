@@ -0,0 +1,365 @@
+//! Drawing the terminal, either into the SDL window or into a buffer in memory. `Painter` works
+//! out what has to be drawn each frame and a `RenderBackend` does the drawing.
+
+use crate::color::{Color, BG_COL, FG_COL};
+use crate::hyperlink::HyperlinkId;
+use crate::selection::{Point, SelectionRange};
+use crate::term::{CharacterCellManager, CursorShape};
+
+pub const MATCH_COL: Color = Color {r: 0xfd, g: 0xbc, b: 0x4b, a: 0};
+pub const CURRENT_MATCH_COL: Color = Color {r: 0xf6, g: 0x74, b: 0x00, a: 0};
+pub const HINT_COL: Color = Color {r: 0xf5, g: 0xd7, b: 0x0a, a: 0};
+
+/// A rectangle in pixels
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+}
+
+/// Everything drawn on top of the terminal contents
+#[derive(Clone, PartialEq, Default)]
+pub struct Overlays {
+    pub selection: Option<SelectionRange>,
+    /// Search matches in view
+    pub matches: Vec<SelectionRange>,
+    pub current_match: Option<SelectionRange>,
+    /// Text shown over the bottom row, like the search prompt
+    pub status_line: Option<String>,
+    /// Cursor of the vi copy mode
+    pub vi_cursor: Option<Point>,
+    /// Hyperlink under the mouse pointer, all of its cells are underlined
+    pub hovered_link: Option<HyperlinkId>,
+    /// URL or path under the pointer while Ctrl is held
+    pub detected_link: Option<SelectionRange>,
+    /// Hint mode labels and where they start, each is drawn over the cells of its match
+    pub hint_labels: Vec<(Point, String)>,
+}
+
+/// Something to draw a frame on. What was drawn is kept between frames, `Painter` only draws
+/// what changed.
+pub trait RenderBackend {
+    /// Makes the frame `width` by `height` pixels. Returns whether it had to be created again,
+    /// which loses what was drawn.
+    fn resize(&mut self, width: u32, height: u32) -> bool;
+
+    /// Moves what was drawn up by `pixels`, or down if negative. The part that is uncovered
+    /// keeps whatever it showed before.
+    fn scroll(&mut self, pixels: i32);
+
+    /// Calls `draw` once with the target the frame is drawn on, so a backend can set up and
+    /// finish drawing once per frame instead of for every cell
+    fn draw(&mut self, draw: &mut dyn FnMut(&mut dyn DrawTarget));
+}
+
+/// What `RenderBackend::draw` hands out to draw with
+pub trait DrawTarget {
+    fn fill_rect(&mut self, rect: Rect, color: Color);
+
+    /// Draws `ch` into `cell`, which was just filled with `bgc`
+    fn draw_char(&mut self, cell: Rect, ch: char, fgc: Color, bgc: Color, bold: bool);
+}
+
+/// The part of the terminal that is shown and how
+#[derive(Copy, Clone)]
+pub struct View {
+    /// Size of the view in cells
+    pub width: usize,
+    pub height: usize,
+    /// How many lines the view is scrolled back into history, 0 shows the live screen
+    pub display_offset: usize,
+    /// Whether the window has keyboard focus, the cursor is drawn hollow without it
+    pub focused: bool,
+    /// Whether a blinking cursor is in the visible half of its blink
+    pub cursor_blink_on: bool,
+}
+
+/// Decides what to draw each frame. It remembers what the previous frame showed, so only the
+/// damaged cells are drawn again and a scrolled screen is moved rather than drawn from scratch.
+pub struct Painter {
+    pub cell_width: u32,
+    pub cell_height: u32,
+    // What the previous frame showed, every cell is redrawn when any of it changes
+    force_redraw: bool,
+    last_overlays: Overlays,
+    last_display_offset: usize,
+    last_lines_scrolled: usize,
+    // Cell the cursor was drawn over, it has to be redrawn once the cursor moves or blinks
+    last_cursor: Option<Point>,
+}
+
+impl Painter {
+    pub fn new(cell_width: u32, cell_height: u32) -> Self {
+        Self {
+            cell_width,
+            cell_height,
+            force_redraw: true,
+            last_overlays: Overlays::default(),
+            last_display_offset: 0,
+            last_lines_scrolled: 0,
+            last_cursor: None,
+        }
+    }
+
+    pub fn set_cell_size(&mut self, cell_width: u32, cell_height: u32) {
+        self.cell_width = cell_width;
+        self.cell_height = cell_height;
+        self.force_redraw = true;
+    }
+
+    /// Draws every cell on the next frame
+    pub fn redraw(&mut self) {
+        self.force_redraw = true;
+    }
+
+    pub fn paint(&mut self, backend: &mut impl RenderBackend, terminal_buffer: &CharacterCellManager, overlays: &Overlays, view: &View) {
+        let display_offset = view.display_offset.min(terminal_buffer.grid.history_len());
+        let top_line = terminal_buffer.lines_scrolled - display_offset;
+        let damage = &terminal_buffer.damage;
+        let resized = backend.resize(view.width as u32 * self.cell_width, view.height as u32 * self.cell_height);
+        // Damage only describes the live screen, so anything else forces a full redraw
+        let force_redraw = std::mem::replace(&mut self.force_redraw, false) || resized;
+        // Labels and the status line would be moved along with the text they are drawn over
+        let labels_shown = overlays.status_line.is_some() || !overlays.hint_labels.is_empty();
+        let redraw_all = force_redraw
+            || *overlays != self.last_overlays
+            || display_offset != self.last_display_offset
+            || (display_offset > 0 && (terminal_buffer.lines_scrolled != self.last_lines_scrolled || damage.scrolled != 0))
+            || (labels_shown && damage.scrolled != 0);
+        self.last_overlays = overlays.clone();
+        self.last_display_offset = display_offset;
+        self.last_lines_scrolled = terminal_buffer.lines_scrolled;
+        if !redraw_all && damage.scrolled != 0 {
+            backend.scroll(damage.scrolled as i32 * self.cell_height as i32);
+        }
+
+        let cursor = terminal_buffer.cursor();
+        let cursor_point = Point::new(terminal_buffer.lines_scrolled + cursor.y, cursor.x);
+        let cursor_in_view = cursor_point.line >= top_line && cursor_point.line < top_line + view.height;
+        let draw_cursor = cursor.visible && cursor_in_view && view.cursor_blink_on;
+        let old_cursor = std::mem::replace(&mut self.last_cursor, if draw_cursor { Some(cursor_point) } else { None });
+
+        backend.draw(&mut |target| {
+            if force_redraw {
+                let (width, height) = (view.width as u32 * self.cell_width, view.height as u32 * self.cell_height);
+                target.fill_rect(Rect::new(0, 0, width, height), BG_COL);
+            }
+            self.draw_cells(target, terminal_buffer, overlays, view, top_line, redraw_all, old_cursor);
+        });
+    }
+
+    /// Draws the cells that changed since the last frame, or all of them if `redraw_all`, and
+    /// the overlays on top
+    #[allow(clippy::too_many_arguments)]
+    fn draw_cells(&self, target: &mut dyn DrawTarget, terminal_buffer: &CharacterCellManager, overlays: &Overlays, view: &View, top_line: usize, redraw_all: bool, old_cursor: Option<Point>) {
+        let damage = &terminal_buffer.damage;
+        let cursor = terminal_buffer.cursor();
+        for y in 0..view.height {
+            let line = top_line + y;
+            let row = match terminal_buffer.line(line) {
+                Some(row) => row,
+                None => continue,
+            };
+            let damaged = match line.checked_sub(terminal_buffer.lines_scrolled) {
+                Some(screen_row) => damage.rows.get(screen_row).cloned().flatten(),
+                None => None,
+            };
+            let has_cursor = |point: Option<Point>| point.is_some_and(|point| point.line == line);
+            if !(redraw_all || damaged.is_some() || has_cursor(old_cursor) || has_cursor(self.last_cursor)) {
+                continue;
+            }
+            for x in 0..view.width {
+                // Rows in history may be narrower if the window was resized
+                let cell = match row.get(x) {
+                    Some(cell) => cell,
+                    None => continue,
+                };
+                let point = Point::new(line, x);
+                let under_cursor = old_cursor == Some(point) || self.last_cursor == Some(point);
+                if !(redraw_all || under_cursor || damaged.as_ref().is_some_and(|cols| cols.contains(&x))) {
+                    continue;
+                }
+                let ch = if cell.ch == '\0' {
+                    ' '
+                } else {
+                    cell.ch
+                };
+
+                let selected = overlays.selection.is_some_and(|sel| sel.contains(point));
+                let (mut fgc, mut bgc) = if cell.inverse() != selected {
+                    (cell.bg_col.rgb(), cell.fg_col.rgb())
+                } else {
+                    (cell.fg_col.rgb(), cell.bg_col.rgb())
+                };
+                if overlays.vi_cursor == Some(point) {
+                    fgc = BG_COL;
+                    bgc = FG_COL;
+                } else if overlays.current_match.is_some_and(|m| m.contains(point)) {
+                    fgc = BG_COL;
+                    bgc = CURRENT_MATCH_COL;
+                } else if overlays.matches.iter().any(|m| m.contains(point)) {
+                    fgc = BG_COL;
+                    bgc = MATCH_COL;
+                }
+
+                self.draw_char(target, x, y, ch, fgc, bgc, cell.bold());
+                let hovered = overlays.hovered_link.is_some() && cell.hyperlink() == overlays.hovered_link;
+                if hovered || overlays.detected_link.is_some_and(|link| link.contains(point)) {
+                    self.draw_underline(target, x, y, fgc);
+                }
+                if self.last_cursor == Some(point) {
+                    self.draw_cursor(target, x, y, ch, fgc, bgc, cell.bold(), cursor.shape, view.focused);
+                }
+            }
+        }
+
+        // Like the status line, labels are drawn on top every frame
+        for (start, label) in &overlays.hint_labels {
+            if start.line < top_line || start.line >= top_line + view.height {
+                continue;
+            }
+            for (i, ch) in label.chars().enumerate().take(view.width.saturating_sub(start.col)) {
+                self.draw_char(target, start.col + i, start.line - top_line, ch, BG_COL, HINT_COL, true);
+            }
+        }
+
+        // The status line is drawn on top of the bottom row every frame
+        if let Some(status) = &overlays.status_line {
+            let mut chars = status.chars();
+            for x in 0..view.width {
+                let ch = chars.next().unwrap_or(' ');
+                self.draw_char(target, x, view.height - 1, ch, BG_COL, FG_COL, false);
+            }
+        }
+    }
+
+    fn cell_rect(&self, x: usize, y: usize) -> Rect {
+        Rect::new((x as u32 * self.cell_width) as i32, (y as u32 * self.cell_height) as i32, self.cell_width, self.cell_height)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_char(&self, target: &mut dyn DrawTarget, x: usize, y: usize, ch: char, fgc: Color, bgc: Color, bold: bool) {
+        let cell = self.cell_rect(x, y);
+        target.fill_rect(cell, bgc);
+        target.draw_char(cell, ch, fgc, bgc, bold);
+    }
+
+    /// Draws the cursor over a cell that was just drawn with the colors `fgc` and `bgc`
+    #[allow(clippy::too_many_arguments)]
+    fn draw_cursor(&self, target: &mut dyn DrawTarget, x: usize, y: usize, ch: char, fgc: Color, bgc: Color, bold: bool, shape: CursorShape, focused: bool) {
+        let cell = self.cell_rect(x, y);
+        if !focused {
+            let (right, bottom) = (cell.x + cell.width as i32 - 1, cell.y + cell.height as i32 - 1);
+            target.fill_rect(Rect::new(cell.x, cell.y, cell.width, 1), fgc);
+            target.fill_rect(Rect::new(cell.x, bottom, cell.width, 1), fgc);
+            target.fill_rect(Rect::new(cell.x, cell.y, 1, cell.height), fgc);
+            target.fill_rect(Rect::new(right, cell.y, 1, cell.height), fgc);
+            return;
+        }
+        match shape {
+            CursorShape::Block => self.draw_char(target, x, y, ch, bgc, fgc, bold),
+            CursorShape::Underline => target.fill_rect(Rect::new(cell.x, cell.y + cell.height as i32 - 2, cell.width, 2), fgc),
+            CursorShape::Bar => target.fill_rect(Rect::new(cell.x, cell.y, 2, cell.height), fgc),
+        }
+    }
+
+    fn draw_underline(&self, target: &mut dyn DrawTarget, x: usize, y: usize, color: Color) {
+        let cell = self.cell_rect(x, y);
+        target.fill_rect(Rect::new(cell.x, cell.y + cell.height as i32 - 1, cell.width, 1), color);
+    }
+}
+
+/// Draws into an RGBA buffer in memory, so frames can be checked without a window. There are no
+/// fonts, every character other than a space is a solid block of the foreground color covering
+/// the middle half of its cell.
+#[derive(Default)]
+pub struct SoftwareBackend {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl SoftwareBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The frame row by row, 4 bytes per pixel in RGBA order
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Color of the pixel at `x`, `y`. Pixels are always opaque.
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        let i = (y * self.width + x) as usize * 4;
+        Color::RGB(self.pixels[i], self.pixels[i + 1], self.pixels[i + 2])
+    }
+}
+
+impl RenderBackend for SoftwareBackend {
+    fn resize(&mut self, width: u32, height: u32) -> bool {
+        if width == self.width && height == self.height {
+            return false;
+        }
+        self.width = width;
+        self.height = height;
+        self.pixels = vec![0; (width * height) as usize * 4];
+        true
+    }
+
+    fn scroll(&mut self, pixels: i32) {
+        let stride = self.width as usize * 4;
+        let shift = (pixels.unsigned_abs() as usize).min(self.height as usize) * stride;
+        let len = self.pixels.len();
+        if pixels > 0 {
+            self.pixels.copy_within(shift.., 0);
+        } else {
+            self.pixels.copy_within(..len - shift, shift);
+        }
+    }
+
+    fn draw(&mut self, draw: &mut dyn FnMut(&mut dyn DrawTarget)) {
+        draw(self);
+    }
+}
+
+impl DrawTarget for SoftwareBackend {
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        // Clipped to the frame like SDL does
+        let left = rect.x.clamp(0, self.width as i32) as u32;
+        let top = rect.y.clamp(0, self.height as i32) as u32;
+        let right = (rect.x + rect.width as i32).clamp(0, self.width as i32) as u32;
+        let bottom = (rect.y + rect.height as i32).clamp(0, self.height as i32) as u32;
+        for y in top..bottom {
+            for x in left..right {
+                let i = (y * self.width + x) as usize * 4;
+                self.pixels[i..i + 4].copy_from_slice(&[color.r, color.g, color.b, 0xff]);
+            }
+        }
+    }
+
+    fn draw_char(&mut self, cell: Rect, ch: char, fgc: Color, _bgc: Color, _bold: bool) {
+        if ch == ' ' {
+            return;
+        }
+        let (width, height) = (cell.width / 2, cell.height / 2);
+        let glyph = Rect::new(cell.x + (cell.width / 4) as i32, cell.y + (cell.height / 4) as i32, width, height);
+        self.fill_rect(glyph, fgc);
+    }
+}
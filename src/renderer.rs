@@ -1,16 +1,141 @@
 extern crate sdl2;
 
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use font_kit::family_name::FamilyName;
 use font_kit::handle::Handle;
+use font_kit::properties::{Properties, Weight};
 use font_kit::source::SystemSource;
-use sdl2::pixels::Color;
-use sdl2::rect::Rect;
-use sdl2::render::WindowCanvas;
+use rust_terminal::color::Color;
+use sdl2::rect::Rect as SdlRect;
+use sdl2::render::{Texture, TextureCreator, WindowCanvas};
+use sdl2::video::WindowContext;
 use sdl2::Sdl;
 use sdl2::ttf::Sdl2TtfContext;
-use sdl2::ttf::Font;
+use sdl2::ttf::{Font, FontStyle};
 use sdl2::VideoSubsystem;
-use crate::term::CharacterCellManager;
+use crate::box_drawing;
+use crate::config::Config;
+use rust_terminal::render::{DrawTarget, Overlays, Painter, Rect, RenderBackend, View};
+use rust_terminal::term::CharacterCellManager;
+use rust_terminal::color::BG_COL;
+
+/// A font file and the index of the face inside it
+#[derive(Clone, PartialEq)]
+struct FontSource {
+    path: PathBuf,
+    index: u32,
+}
+
+impl FontSource {
+    fn from_handle(handle: Handle) -> Option<Self> {
+        match handle {
+            Handle::Path { path, font_index } => Some(Self { path, index: font_index }),
+            Handle::Memory { .. } => None,
+        }
+    }
+
+    fn load<'a>(&self, ttf_context: &'a Sdl2TtfContext, size: u16) -> Result<Font<'a, 'a>, String> {
+        ttf_context.load_font_at_index(&self.path, self.index, size)
+    }
+}
+
+/// A font from the fallback chain, used for characters the main font doesn't have
+struct FallbackFont<'a> {
+    source: FontSource,
+    font: Font<'a, 'a>,
+    // SDL_ttf can only look up glyphs in the basic multilingual plane, so coverage is checked here
+    coverage: font_kit::font::Font,
+}
+
+/// Finds `family`, or any monospace font if it isn't installed
+fn find_font(family: &str, bold: bool) -> Option<FontSource> {
+    let mut properties = Properties::new();
+    if bold {
+        properties.weight(Weight::BOLD);
+    }
+    let families = [FamilyName::Title(family.to_string()), FamilyName::Monospace];
+    FontSource::from_handle(SystemSource::new().select_best_match(&families, &properties).ok()?)
+}
+
+fn load_fallback_fonts<'a>(ttf_context: &'a Sdl2TtfContext, families: &[String], size: u16) -> Vec<FallbackFont<'a>> {
+    let source = SystemSource::new();
+    families.iter()
+        .filter_map(|family| {
+            let handle = source.select_family_by_name(family).ok()?.fonts().first()?.clone();
+            let coverage = handle.load().ok()?;
+            let source = FontSource::from_handle(handle)?;
+            let font = source.load(ttf_context, size).ok()?;
+            Some(FallbackFont { source, font, coverage })
+        })
+        .collect()
+}
+
+/// Size of a cell, taken from the width of a glyph since the font is monospace
+fn cell_size(font: &Font) -> (u32, u32) {
+    let char_surf = font.render_char('i')
+        .shaded(Color{r:0,g:0,b:0,a:0}, Color{r:0,g:0,b:0,a:0})
+        .unwrap();
+    (char_surf.width(), char_surf.height())
+}
+
+/// The grid as it was drawn last frame. It's kept in a texture so only what changed has to be
+/// drawn again, and moved when the screen scrolls instead of being drawn from scratch.
+pub struct Frame<'t> {
+    texture_creator: &'t TextureCreator<WindowContext>,
+    front: Option<Texture<'t>>,
+    // A texture can't be copied onto itself, scrolling copies the front into this one and swaps
+    back: Option<Texture<'t>>,
+}
+
+impl<'t> Frame<'t> {
+    pub fn new(texture_creator: &'t TextureCreator<WindowContext>) -> Self {
+        Self {
+            texture_creator,
+            front: None,
+            back: None,
+        }
+    }
+
+    /// Makes the textures `width` by `height` pixels. Returns whether they had to be created
+    /// again, which loses what was drawn.
+    fn fit(&mut self, width: u32, height: u32) -> bool {
+        let fits = self.front.as_ref().is_some_and(|texture| {
+            let query = texture.query();
+            query.width == width && query.height == height
+        });
+        if fits {
+            return false;
+        }
+        let texture_creator = self.texture_creator;
+        let create = || texture_creator.create_texture_target(None, width.max(1), height.max(1)).unwrap();
+        self.front = Some(create());
+        self.back = Some(create());
+        true
+    }
+
+    /// Moves what was drawn up by `pixels`, or down if negative. The part that is uncovered keeps
+    /// stale content and has to be drawn again.
+    fn scroll(&mut self, canvas: &mut WindowCanvas, pixels: i32) {
+        let (front, back) = match (self.front.as_ref(), self.back.as_mut()) {
+            (Some(front), Some(back)) => (front, back),
+            _ => return,
+        };
+        let query = front.query();
+        let shift = pixels.unsigned_abs().min(query.height);
+        let kept = query.height - shift;
+        let (src_y, dst_y) = if pixels > 0 { (shift, 0) } else { (0, shift) };
+        canvas.with_texture_canvas(back, |target| {
+            if kept > 0 {
+                let src = SdlRect::new(0, src_y as i32, query.width, kept);
+                let dst = SdlRect::new(0, dst_y as i32, query.width, kept);
+                target.copy(front, src, dst).unwrap();
+            }
+        }).unwrap();
+        std::mem::swap(&mut self.front, &mut self.back);
+    }
+}
 
 pub struct TermRenderer<'a> {
     width: usize,
@@ -22,81 +147,301 @@ pub struct TermRenderer<'a> {
     pub ttf_context: &'a Sdl2TtfContext,
     pub font: Font<'a, 'a>,
     pub bold_font: Font<'a, 'a>,
+    /// Point size the fonts are loaded at
+    pub font_size: u16,
+    regular_source: FontSource,
+    bold_source: FontSource,
+    fallback_fonts: Vec<FallbackFont<'a>>,
+    // Which fallback font draws a character, `None` if none of them has it
+    fallback_cache: HashMap<char, Option<usize>>,
+    builtin_box_drawing: bool,
     pub video_subsystem: VideoSubsystem,
+
+    /// How many lines the view is scrolled back into history, 0 shows the live screen
+    pub display_offset: usize,
+    /// Whether the window has keyboard focus, the cursor is drawn hollow without it
+    pub focused: bool,
+    cursor_blink_interval: Option<Duration>,
+    blink_epoch: Instant,
+    painter: Painter,
 }
 
 impl<'a> TermRenderer<'a> {
-    fn get_font_path(name: &str) -> Option<PathBuf> {
-        match SystemSource::new().select_by_postscript_name(name).ok()? {
-            Handle::Path { path, .. } => Some(path),
-            Handle::Memory { .. } => None,
+    pub fn new(sdl_context: &'a Sdl, ttf_context: &'a Sdl2TtfContext, config: &Config, width: usize, height: usize) -> TermRenderer<'a> {
+        if SystemSource::new().select_family_by_name(&config.font_family).is_err() {
+            eprintln!("Warning: Font {:?} not found, using the default monospace font", config.font_family);
         }
-    }
-
-    pub fn new(sdl_context: &'a Sdl, ttf_context: &'a Sdl2TtfContext, width: usize, height: usize) -> TermRenderer<'a> {
-        let hack_regular = Self::get_font_path("Hack-Regular").unwrap();
-        let hack_bold = Self::get_font_path("Hack-Bold").unwrap();
-        let font = ttf_context.load_font(hack_regular, 15).unwrap();
-        let bold_font = ttf_context.load_font(hack_bold, 15).unwrap();
-
-        let char_surf = font.render_char('i')
-            .shaded(Color{r:0,g:0,b:0,a:0}, Color{r:0,g:0,b:0,a:0})
-            .unwrap();
+        let regular = find_font(&config.font_family, false).expect("No monospace font installed");
+        let bold = find_font(&config.font_family, true).unwrap_or_else(|| regular.clone());
+        let (font, bold_font) = Self::load_fonts(ttf_context, &regular, &bold, config.font_size).unwrap();
+        let fallback_fonts = load_fallback_fonts(ttf_context, &config.font_fallback, config.font_size);
+        let (font_width, font_height) = cell_size(&font);
 
         let video_subsystem = sdl_context.video().unwrap();
         let this = Self {
             width,
             height,
-            font_width: char_surf.width(),
-            font_height: char_surf.height(),
+            font_width,
+            font_height,
             sdl_context,
             ttf_context,
-            //font: ttf_context.load_font("/usr/share/fonts/TTF/DroidSansMono.ttf", 15).unwrap(),
             font,
             bold_font,
+            font_size: config.font_size,
+            regular_source: regular,
+            bold_source: bold,
+            fallback_fonts,
+            fallback_cache: HashMap::new(),
+            builtin_box_drawing: config.builtin_box_drawing,
             video_subsystem,
+            display_offset: 0,
+            focused: true,
+            cursor_blink_interval: match config.cursor_blink_interval_ms {
+                0 => None,
+                ms => Some(Duration::from_millis(ms)),
+            },
+            blink_epoch: Instant::now(),
+            painter: Painter::new(font_width, font_height),
         };
 
         this
     }
 
-    pub fn render(&mut self, terminal_buffer: &CharacterCellManager, canvas: &mut WindowCanvas) {
-        let tex_creator = canvas.texture_creator();
-        let cells = &terminal_buffer.cells;
-
-        for x in 0..self.width {
-            for y in 0..self.height {
-                let cell = cells.get(y).unwrap().get(x).unwrap();
-                if !cell.dirty {
-                    continue;
-                }
-                let ch = if cell.ch == '\0' {
-                    ' '
-                } else {
-                    cell.ch
-                };
-
-                let (fgc, bgc) = if cell.inverse {
-                    (cell.bg_col, cell.fg_col)
-                } else {
-                    (cell.fg_col, cell.bg_col)
-                };
-
-                let text_surf = if cell.bold {
-                    self.bold_font.render_char(ch)
-                        .shaded(fgc, bgc)
-                        .unwrap()
-                } else {
-                    self.font.render_char(ch)
-                        .shaded(fgc, bgc)
-                        .unwrap()
-                };
-
-                let text_texture = text_surf.as_texture(&tex_creator).unwrap();
-                let text_rect = Rect::new(0, 0, self.font_width, self.font_height);
-                let real_rect = Rect::new((x * ((self.font_width) as usize)) as i32, (y * (self.font_height as usize)) as i32, self.font_width, self.font_height);
-                canvas.copy(&text_texture, text_rect, real_rect).unwrap();
+    fn load_fonts(ttf_context: &'a Sdl2TtfContext, regular: &FontSource, bold: &FontSource, size: u16) -> Result<(Font<'a, 'a>, Font<'a, 'a>), String> {
+        let font = regular.load(ttf_context, size)?;
+        let mut bold_font = bold.load(ttf_context, size)?;
+        // Embolden the regular face if the family has no bold one
+        if bold == regular {
+            bold_font.set_style(FontStyle::BOLD);
+        }
+        Ok((font, bold_font))
+    }
+
+    /// Reloads every font at `size` points and updates the cell size. The grid keeps its
+    /// dimensions, `resize` has to be called if the window size stays the same.
+    pub fn set_font_size(&mut self, size: u16) -> Result<(), String> {
+        let (font, bold_font) = Self::load_fonts(self.ttf_context, &self.regular_source, &self.bold_source, size)?;
+        let fallback_fonts = self.fallback_fonts.iter()
+            .map(|fallback| fallback.source.load(self.ttf_context, size))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (fallback, font) in self.fallback_fonts.iter_mut().zip(fallback_fonts) {
+            fallback.font = font;
+        }
+        (self.font_width, self.font_height) = cell_size(&font);
+        self.font = font;
+        self.bold_font = bold_font;
+        self.font_size = size;
+        self.fallback_cache.clear();
+        self.painter.set_cell_size(self.font_width, self.font_height);
+        Ok(())
+    }
+
+    /// Changes the grid size in cells
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.painter.redraw();
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the 0-based column and row of the cell under the given window coordinates.
+    pub fn cell_at(&self, x: i32, y: i32) -> (usize, usize) {
+        let col = (x.max(0) as u32 / self.font_width) as usize;
+        let row = (y.max(0) as u32 / self.font_height) as usize;
+        (col.min(self.width - 1), row.min(self.height - 1))
+    }
+
+    /// Absolute line number of the top row of the view
+    pub fn top_line(&self, terminal_buffer: &CharacterCellManager) -> usize {
+        terminal_buffer.lines_scrolled - self.display_offset.min(terminal_buffer.grid.history_len())
+    }
+
+    /// Scrolls the view `lines` lines back into history, or towards the live screen if negative
+    pub fn scroll_viewport(&mut self, terminal_buffer: &CharacterCellManager, lines: isize) {
+        let offset = self.display_offset as isize + lines;
+        self.display_offset = (offset.max(0) as usize).min(terminal_buffer.grid.history_len());
+    }
+
+    /// Scrolls the view so the absolute line `line` is centered, unless it's already visible
+    pub fn scroll_to_line(&mut self, terminal_buffer: &CharacterCellManager, line: usize) {
+        let top = self.top_line(terminal_buffer);
+        if line >= top && line < top + self.height {
+            return;
+        }
+        let top = line.saturating_sub(self.height / 2);
+        self.display_offset = terminal_buffer.lines_scrolled.saturating_sub(top).min(terminal_buffer.grid.history_len());
+    }
+
+    /// Scrolls the view as little as possible to make the absolute line `line` visible
+    pub fn reveal_line(&mut self, terminal_buffer: &CharacterCellManager, line: usize) {
+        let top = self.top_line(terminal_buffer);
+        let top = if line < top {
+            line
+        } else if line >= top + self.height {
+            line + 1 - self.height
+        } else {
+            return;
+        };
+        self.display_offset = terminal_buffer.lines_scrolled.saturating_sub(top).min(terminal_buffer.grid.history_len());
+    }
+
+    pub fn scroll_to_top(&mut self, terminal_buffer: &CharacterCellManager) {
+        self.display_offset = terminal_buffer.grid.history_len();
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        self.display_offset = 0;
+    }
+
+    /// Keeps the cursor solid for a moment, called on input so it doesn't vanish while typing
+    pub fn reset_cursor_blink(&mut self) {
+        self.blink_epoch = Instant::now();
+    }
+
+    /// How long until a blinking cursor turns on or off, `None` if it doesn't blink
+    pub fn next_blink(&self, blinking: bool) -> Option<Duration> {
+        match self.cursor_blink_interval {
+            Some(interval) if blinking && self.focused => {
+                let interval = interval.as_millis().max(1);
+                let left = interval - self.blink_epoch.elapsed().as_millis() % interval;
+                Some(Duration::from_millis(left as u64))
             }
+            _ => None,
         }
     }
-}
\ No newline at end of file
+
+    fn cursor_blink_on(&self, blinking: bool) -> bool {
+        match self.cursor_blink_interval {
+            Some(interval) if blinking && self.focused => {
+                (self.blink_epoch.elapsed().as_millis() / interval.as_millis()) & 1 == 0
+            }
+            _ => true,
+        }
+    }
+
+    pub fn render(&mut self, terminal_buffer: &CharacterCellManager, overlays: &Overlays, frame: &mut Frame, canvas: &mut WindowCanvas) {
+        self.display_offset = self.display_offset.min(terminal_buffer.grid.history_len());
+        let view = View {
+            width: self.width,
+            height: self.height,
+            display_offset: self.display_offset,
+            focused: self.focused,
+            cursor_blink_on: self.cursor_blink_on(terminal_buffer.cursor().blinking),
+        };
+        let tex_creator = canvas.texture_creator();
+        let mut backend = SdlBackend {
+            canvas,
+            frame,
+            glyphs: Glyphs {
+                tex_creator: &tex_creator,
+                font: &self.font,
+                bold_font: &self.bold_font,
+                fallback_fonts: &self.fallback_fonts,
+                fallback_cache: &mut self.fallback_cache,
+                builtin_box_drawing: self.builtin_box_drawing,
+            },
+        };
+        self.painter.paint(&mut backend, terminal_buffer, overlays, &view);
+
+        // Clears the margin left when the window isn't a whole number of cells
+        canvas.set_draw_color(BG_COL);
+        canvas.clear();
+        if let Some(texture) = frame.front.as_ref() {
+            let query = texture.query();
+            canvas.copy(texture, None, SdlRect::new(0, 0, query.width, query.height)).unwrap();
+        }
+    }
+}
+
+/// Draws into the textures of a `Frame`
+struct SdlBackend<'r, 'a, 't> {
+    canvas: &'r mut WindowCanvas,
+    frame: &'r mut Frame<'t>,
+    glyphs: Glyphs<'r, 'a>,
+}
+
+/// What characters are drawn with
+struct Glyphs<'r, 'a> {
+    tex_creator: &'r TextureCreator<WindowContext>,
+    font: &'r Font<'a, 'a>,
+    bold_font: &'r Font<'a, 'a>,
+    fallback_fonts: &'r [FallbackFont<'a>],
+    fallback_cache: &'r mut HashMap<char, Option<usize>>,
+    builtin_box_drawing: bool,
+}
+
+impl Glyphs<'_, '_> {
+    /// Index of the fallback font to draw `ch` with, `None` to use the main font
+    fn fallback_for(&mut self, ch: char, bold: bool) -> Option<usize> {
+        let font = if bold { self.bold_font } else { self.font };
+        if ch.is_ascii() || (ch as u32 <= 0xffff && font.find_glyph(ch).is_some()) {
+            return None;
+        }
+        let fallback_fonts = self.fallback_fonts;
+        *self.fallback_cache.entry(ch).or_insert_with(|| {
+            fallback_fonts.iter().position(|fallback| fallback.coverage.glyph_for_char(ch).is_some())
+        })
+    }
+}
+
+impl RenderBackend for SdlBackend<'_, '_, '_> {
+    fn resize(&mut self, width: u32, height: u32) -> bool {
+        self.frame.fit(width, height)
+    }
+
+    fn scroll(&mut self, pixels: i32) {
+        self.frame.scroll(self.canvas, pixels);
+    }
+
+    fn draw(&mut self, draw: &mut dyn FnMut(&mut dyn DrawTarget)) {
+        let texture = match self.frame.front.as_mut() {
+            Some(texture) => texture,
+            None => return,
+        };
+        let glyphs = &mut self.glyphs;
+        // The front texture stays the render target for the whole frame
+        self.canvas.with_texture_canvas(texture, |canvas| draw(&mut SdlTarget { canvas, glyphs })).unwrap();
+    }
+}
+
+/// The front texture of a `Frame` while it's the render target
+struct SdlTarget<'c, 'r, 'a> {
+    canvas: &'c mut WindowCanvas,
+    glyphs: &'c mut Glyphs<'r, 'a>,
+}
+
+impl DrawTarget for SdlTarget<'_, '_, '_> {
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        self.canvas.set_draw_color(color);
+        self.canvas.fill_rect(SdlRect::new(rect.x, rect.y, rect.width, rect.height)).unwrap();
+    }
+
+    fn draw_char(&mut self, cell: Rect, ch: char, fgc: Color, bgc: Color, bold: bool) {
+        let real_rect = SdlRect::new(cell.x, cell.y, cell.width, cell.height);
+        if self.glyphs.builtin_box_drawing && box_drawing::draw(self.canvas, real_rect, ch, fgc, bgc) {
+            return;
+        }
+        let font = match self.glyphs.fallback_for(ch, bold) {
+            Some(i) => &self.glyphs.fallback_fonts[i].font,
+            None if bold => self.glyphs.bold_font,
+            None => self.glyphs.font,
+        };
+        // Glyphs with no width, like combining marks on their own, can't be rendered
+        let text_surf = match font.render_char(ch).shaded(fgc, bgc) {
+            Ok(surf) => surf,
+            Err(_) => return,
+        };
+
+        // Fallback glyphs can be bigger than a cell, they are cut off instead of overlapping
+        let (w, h) = (text_surf.width().min(cell.width), text_surf.height().min(cell.height));
+        let text_texture = text_surf.as_texture(self.glyphs.tex_creator).unwrap();
+        self.canvas.copy(&text_texture, SdlRect::new(0, 0, w, h), SdlRect::new(real_rect.x(), real_rect.y(), w, h)).unwrap();
+    }
+}
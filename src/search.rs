@@ -0,0 +1,151 @@
+use regex::{Regex, RegexBuilder};
+use sdl2::keyboard::Keycode;
+use rust_terminal::selection::{logical_lines, SelectionRange};
+use rust_terminal::term::CharacterCellManager;
+
+pub enum SearchAction {
+    None,
+    /// The query changed and the matches have to be recomputed
+    Changed,
+    /// Move to the next match, towards older output if `backwards`
+    Jump { backwards: bool },
+    Close,
+}
+
+/// Search through the screen and history. Matches are kept as absolute line ranges so they stay
+/// valid while output scrolls.
+#[derive(Default)]
+pub struct Search {
+    pub query: String,
+    /// The prompt is open and taking keyboard input. Copy mode closes it on Enter but keeps the
+    /// matches around for `n` and `N`.
+    pub active: bool,
+    /// Treat the query as a regex instead of literal text
+    pub regex: bool,
+    matches: Vec<SelectionRange>,
+    current: Option<usize>,
+    error: Option<String>,
+    compiled: Option<Regex>,
+    // First absolute line that can have changed since the last update, the top of the screen
+    // back then
+    rescan_from: usize,
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self {
+            active: true,
+            ..Self::default()
+        }
+    }
+
+    /// Enter and Shift+Enter move between matches, Ctrl+R toggles regex mode and Escape closes
+    /// the search.
+    pub fn handle_key(&mut self, keycode: Keycode, shift: bool, ctrl: bool) -> SearchAction {
+        match keycode {
+            Keycode::Escape => SearchAction::Close,
+            Keycode::Return | Keycode::KpEnter => SearchAction::Jump { backwards: !shift },
+            Keycode::Backspace => {
+                self.query.pop();
+                SearchAction::Changed
+            }
+            Keycode::R if ctrl => {
+                self.regex = !self.regex;
+                SearchAction::Changed
+            }
+            _ => SearchAction::None,
+        }
+    }
+
+    fn build_regex(&self) -> Result<Regex, regex::Error> {
+        let pattern = if self.regex {
+            self.query.clone()
+        } else {
+            regex::escape(&self.query)
+        };
+        // Smart case: only match case if the query has upper case letters
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.query.chars().any(char::is_uppercase))
+            .build()
+    }
+
+    /// Recomputes the matches after the query changed
+    pub fn restart(&mut self, cells: &CharacterCellManager) {
+        self.matches.clear();
+        self.current = None;
+        self.error = None;
+        self.compiled = None;
+        self.rescan_from = 0;
+        if !self.query.is_empty() {
+            match self.build_regex() {
+                Ok(regex) => self.compiled = Some(regex),
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+        self.update(cells);
+    }
+
+    /// Searches the output that arrived since the last update, keeping the current match
+    /// selected if it's still there. Lines don't change once they're in history, so only the
+    /// lines that were on the screen last time and those after them are searched again.
+    pub fn update(&mut self, cells: &CharacterCellManager) {
+        let regex = match &self.compiled {
+            Some(regex) => regex,
+            None => return,
+        };
+        let current = self.current_match();
+        // Matches don't cross logical lines, so the rescan starts at the beginning of one
+        let mut start = self.rescan_from.max(cells.first_line());
+        while start > cells.first_line() && cells.is_wrapped(start - 1) {
+            start -= 1;
+        }
+        self.matches.retain(|m| m.start.line >= cells.first_line() && m.start.line < start);
+        for line in logical_lines(cells, start, cells.last_line()) {
+            for m in regex.find_iter(&line.text) {
+                if let Some(range) = line.range(m.start(), m.end()) {
+                    self.matches.push(range);
+                }
+            }
+        }
+        self.rescan_from = cells.lines_scrolled;
+        self.current = current.and_then(|current| self.matches.iter().position(|m| *m == current));
+    }
+
+    /// Selects the next match and returns it. Without a current match it starts from the view
+    /// spanning the absolute lines `top..=bottom`.
+    pub fn jump(&mut self, backwards: bool, top: usize, bottom: usize) -> Option<SelectionRange> {
+        let len = self.matches.len();
+        if len == 0 {
+            return None;
+        }
+        let next = match self.current {
+            Some(i) if backwards => (i + len - 1) % len,
+            Some(i) => (i + 1) % len,
+            None if backwards => self.matches.iter().rposition(|m| m.start.line <= bottom).unwrap_or(len - 1),
+            None => self.matches.iter().position(|m| m.start.line >= top).unwrap_or(0),
+        };
+        self.current = Some(next);
+        self.current_match()
+    }
+
+    pub fn current_match(&self) -> Option<SelectionRange> {
+        self.matches.get(self.current?).copied()
+    }
+
+    /// Matches touching the absolute lines `top..=bottom`
+    pub fn visible_matches(&self, top: usize, bottom: usize) -> Vec<SelectionRange> {
+        self.matches.iter()
+            .filter(|m| m.end.line >= top && m.start.line <= bottom)
+            .copied()
+            .collect()
+    }
+
+    pub fn status_line(&self) -> String {
+        let mode = if self.regex { "Regex" } else { "Search" };
+        match (&self.error, self.current) {
+            (Some(e), _) => format!("{mode}: {} [{}]", self.query, e.lines().last().unwrap_or("")),
+            (None, Some(i)) => format!("{mode}: {} [{}/{}]", self.query, i + 1, self.matches.len()),
+            (None, None) => format!("{mode}: {} [{}]", self.query, self.matches.len()),
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use crate::term::CharacterCellManager;
+
+/// Characters that end a word for double-click selection
+const WORD_SEPARATORS: &str = " \t\"'`()[]{}<>,;|";
+
+/// A cell addressed by absolute line number (see `CharacterCellManager::lines_scrolled`) and
+/// 0-based column. Ordered by line first so points compare in reading order.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Point {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Point {
+    pub fn new(line: usize, col: usize) -> Self {
+        Self { line, col }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SelectionKind {
+    Simple,
+    Word,
+    Line,
+    Block,
+}
+
+/// The cells covered by a selection once word and line expansion has been applied
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct SelectionRange {
+    pub start: Point,
+    pub end: Point,
+    pub block: bool,
+}
+
+impl SelectionRange {
+    pub fn contains(&self, point: Point) -> bool {
+        if point.line < self.start.line || point.line > self.end.line {
+            return false;
+        }
+        if self.block {
+            let (left, right) = (self.start.col.min(self.end.col), self.start.col.max(self.end.col));
+            return point.col >= left && point.col <= right;
+        }
+        (point.line > self.start.line || point.col >= self.start.col)
+            && (point.line < self.end.line || point.col <= self.end.col)
+    }
+}
+
+pub struct Selection {
+    pub kind: SelectionKind,
+    anchor: Point,
+    head: Point,
+}
+
+impl Selection {
+    pub fn new(kind: SelectionKind, point: Point) -> Self {
+        Self {
+            kind,
+            anchor: point,
+            head: point,
+        }
+    }
+
+    /// Moves the end of the selection that follows the pointer
+    pub fn update(&mut self, point: Point) {
+        self.head = point;
+    }
+
+    /// A simple selection that was never dragged doesn't select anything
+    pub fn is_empty(&self) -> bool {
+        self.kind == SelectionKind::Simple && self.anchor == self.head
+    }
+
+    pub fn range(&self, cells: &CharacterCellManager) -> SelectionRange {
+        let (mut start, mut end) = if self.anchor <= self.head {
+            (self.anchor, self.head)
+        } else {
+            (self.head, self.anchor)
+        };
+        match self.kind {
+            SelectionKind::Simple | SelectionKind::Block => {}
+            SelectionKind::Word => {
+                start = word_start(cells, start);
+                end = word_end(cells, end);
+            }
+            SelectionKind::Line => {
+                // Soft-wrapped rows belong to the same line
+                while start.line > cells.first_line() && cells.is_wrapped(start.line - 1) {
+                    start.line -= 1;
+                }
+                while end.line < cells.last_line() && cells.is_wrapped(end.line) {
+                    end.line += 1;
+                }
+                start.col = 0;
+                end.col = cells.line(end.line).map_or(0, |row| row.len().saturating_sub(1));
+            }
+        }
+        SelectionRange {
+            start,
+            end,
+            block: self.kind == SelectionKind::Block,
+        }
+    }
+
+    pub fn text(&self, cells: &CharacterCellManager) -> String {
+        range_text(cells, self.range(cells))
+    }
+}
+
+fn is_word_char(cells: &CharacterCellManager, point: Point) -> bool {
+    cells.line(point.line)
+        .and_then(|row| row.get(point.col))
+        .is_some_and(|cell| !WORD_SEPARATORS.contains(cell.ch) && cell.ch != '\0')
+}
+
+/// Walks left from `point` to the first character of the word, following soft wraps
+fn word_start(cells: &CharacterCellManager, mut point: Point) -> Point {
+    if !is_word_char(cells, point) {
+        return point;
+    }
+    loop {
+        let prev = if point.col > 0 {
+            Point::new(point.line, point.col - 1)
+        } else if point.line > cells.first_line() && cells.is_wrapped(point.line - 1) {
+            let len = cells.line(point.line - 1).map_or(0, |row| row.len());
+            Point::new(point.line - 1, len.saturating_sub(1))
+        } else {
+            return point;
+        };
+        if !is_word_char(cells, prev) {
+            return point;
+        }
+        point = prev;
+    }
+}
+
+/// Walks right from `point` to the last character of the word, following soft wraps
+fn word_end(cells: &CharacterCellManager, mut point: Point) -> Point {
+    if !is_word_char(cells, point) {
+        return point;
+    }
+    loop {
+        let len = cells.line(point.line).map_or(0, |row| row.len());
+        let next = if point.col + 1 < len {
+            Point::new(point.line, point.col + 1)
+        } else if point.line < cells.last_line() && cells.is_wrapped(point.line) {
+            Point::new(point.line + 1, 0)
+        } else {
+            return point;
+        };
+        if !is_word_char(cells, next) {
+            return point;
+        }
+        point = next;
+    }
+}
+
+/// Extracts the text in `range`. Rows that were soft-wrapped are joined without a newline and
+/// trailing blanks are trimmed from every line.
+pub fn range_text(cells: &CharacterCellManager, range: SelectionRange) -> String {
+    let mut res = String::new();
+    for line in range.start.line..=range.end.line {
+        let row = match cells.line(line) {
+            Some(row) => row,
+            None => continue,
+        };
+        let (from, to) = if range.block {
+            (range.start.col.min(range.end.col), range.start.col.max(range.end.col))
+        } else {
+            let from = if line == range.start.line { range.start.col } else { 0 };
+            let to = if line == range.end.line { range.end.col } else { row.len().saturating_sub(1) };
+            (from, to)
+        };
+
+        let mut text = String::new();
+        for cell in row.iter().take(to + 1).skip(from) {
+            text.push(if cell.ch == '\0' { ' ' } else { cell.ch });
+        }
+        let joined = !range.block && line != range.end.line && cells.is_wrapped(line);
+        if joined {
+            res.push_str(&text);
+        } else {
+            res.push_str(text.trim_end());
+            if line != range.end.line {
+                res.push('\n');
+            }
+        }
+    }
+    res
+}
+
+/// The text of a line as the program printed it, with soft-wrapped rows joined back together
+pub struct LogicalLine {
+    pub text: String,
+    // Byte offset in `text` and cell of every character
+    chars: Vec<(usize, Point)>,
+}
+
+impl LogicalLine {
+    /// The cells covered by the bytes `start..end` of `text`
+    pub fn range(&self, start: usize, end: usize) -> Option<SelectionRange> {
+        if start >= end {
+            return None;
+        }
+        let first = self.chars.binary_search_by_key(&start, |c| c.0).ok()?;
+        let last = match self.chars.binary_search_by_key(&end, |c| c.0) {
+            Ok(i) | Err(i) => i.checked_sub(1)?,
+        };
+        Some(SelectionRange {
+            start: self.chars[first].1,
+            end: self.chars.get(last)?.1,
+            block: false,
+        })
+    }
+}
+
+/// Extracts the logical lines overlapping the absolute lines `from..=to`
+pub fn logical_lines(cells: &CharacterCellManager, from: usize, to: usize) -> Vec<LogicalLine> {
+    let mut line = from.max(cells.first_line());
+    while line > cells.first_line() && cells.is_wrapped(line - 1) {
+        line -= 1;
+    }
+    let to = to.min(cells.last_line());
+
+    let mut res = Vec::new();
+    while line <= to {
+        let mut logical = LogicalLine {
+            text: String::new(),
+            chars: Vec::new(),
+        };
+        loop {
+            if let Some(row) = cells.line(line) {
+                for (col, cell) in row.iter().enumerate() {
+                    logical.chars.push((logical.text.len(), Point::new(line, col)));
+                    logical.text.push(if cell.ch == '\0' { ' ' } else { cell.ch });
+                }
+            }
+            line += 1;
+            if line > cells.last_line() || !cells.is_wrapped(line - 1) {
+                break;
+            }
+        }
+        res.push(logical);
+    }
+    res
+}
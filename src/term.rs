@@ -1,39 +1,332 @@
 #![allow(unused_variables, dead_code)]
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::ops::Range;
 use rust_ansi::term::TermInterface;
-use sdl2::pixels::Color;
-use crate::text_area::{BG_COL, BOLD_COL_ARR, COL_ARR, FG_COL};
+use crate::grid::{Grid, Row};
+use crate::hyperlink::{self, HyperlinkId, Hyperlinks};
+use crate::color::CellColor;
+
+/// Attributes of a cell, packed into one byte
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub struct Flags(u8);
+
+impl Flags {
+    pub const BOLD: Flags = Flags(1);
+    pub const INVERSE: Flags = Flags(1 << 1);
+    /// Set on the last cell of a row when the text continues on the next row
+    pub const WRAP: Flags = Flags(1 << 2);
+
+    pub fn contains(self, flags: Flags) -> bool {
+        self.0 & flags.0 == flags.0
+    }
+
+    pub fn set(&mut self, flags: Flags, value: bool) {
+        if value {
+            self.0 |= flags.0;
+        } else {
+            self.0 &= !flags.0;
+        }
+    }
+}
+
+/// Most bytes a `CharacterCell` may take. Every line of history is a row of cells, at 16 bytes
+/// 100 000 lines of 80 columns take about 122 MiB.
+pub const CELL_SIZE_BUDGET: usize = 16;
+
+// 4 bytes each for the character, the two colors, and the flags together with the hyperlink
+const _: () = assert!(std::mem::size_of::<CharacterCell>() <= CELL_SIZE_BUDGET);
 
 #[derive(Copy, Clone)]
 pub struct CharacterCell {
     pub ch: char,
-    pub fg_col: Color,
-    pub bg_col: Color,
-    pub bold: bool,
-    pub inverse: bool,
-    pub dirty: bool,
+    pub fg_col: CellColor,
+    pub bg_col: CellColor,
+    // `Flags` in the low byte and the hyperlink id above it, 0 for no hyperlink
+    attrs: u32,
 }
 
 impl CharacterCell {
-    pub fn new(ch: char, fg_col: Color, bg_col: Color, bold: bool, inverse: bool, dirty: bool) -> Self {
+    pub fn new(ch: char, fg_col: CellColor, bg_col: CellColor, flags: Flags) -> Self {
         Self {
             ch,
             fg_col,
             bg_col,
-            bold,
-            inverse,
-            dirty,
+            attrs: flags.0 as u32,
         }
     }
+
+    pub fn flags(&self) -> Flags {
+        Flags(self.attrs as u8)
+    }
+
+    pub fn set_flags(&mut self, flags: Flags) {
+        self.attrs = self.attrs & !0xff | flags.0 as u32;
+    }
+
+    pub fn bold(&self) -> bool {
+        self.flags().contains(Flags::BOLD)
+    }
+
+    pub fn inverse(&self) -> bool {
+        self.flags().contains(Flags::INVERSE)
+    }
+
+    pub fn wrap(&self) -> bool {
+        self.flags().contains(Flags::WRAP)
+    }
+
+    pub fn set_wrap(&mut self, wrap: bool) {
+        let mut flags = self.flags();
+        flags.set(Flags::WRAP, wrap);
+        self.set_flags(flags);
+    }
+
+    /// OSC 8 hyperlink the cell is part of, the URI is looked up in `CharacterCellManager::hyperlinks`
+    pub fn hyperlink(&self) -> Option<HyperlinkId> {
+        HyperlinkId::from_bits(self.attrs >> 8)
+    }
+
+    pub fn set_hyperlink(&mut self, hyperlink: Option<HyperlinkId>) {
+        self.attrs = self.attrs & 0xff | hyperlink.map_or(0, |id| id.bits() << 8);
+    }
 }
 
 impl Default for CharacterCell {
     fn default() -> Self {
-        Self::new(' ', Color::WHITE, Color::BLACK, false, false, true)
+        Self::new(' ', CellColor::Rgb(255, 255, 255), CellColor::Rgb(0, 0, 0), Flags::default())
     }
 }
 
+/// Which mouse events the application wants reported (DEC private modes 9, 1000, 1002, 1003)
+#[derive(Copy, Clone, PartialEq, Eq, Default)]
+pub enum MouseTracking {
+    #[default]
+    Off,
+    X10,
+    Normal,
+    ButtonEvent,
+    AnyEvent,
+}
+
+/// How mouse reports are encoded (DEC private modes 1006 and 1015)
+#[derive(Copy, Clone, PartialEq, Eq, Default)]
+pub enum MouseEncoding {
+    #[default]
+    Default,
+    Sgr,
+    Urxvt,
+}
+
+/// Terminal modes requested by the application that the front end has to act on.
+#[derive(Copy, Clone, Default)]
+pub struct TerminalModes {
+    pub bracketed_paste: bool,
+    pub mouse_tracking: MouseTracking,
+    pub mouse_encoding: MouseEncoding,
+    /// Mode 2026, the program is in the middle of an update that shouldn't be drawn yet
+    pub synchronized_output: bool,
+}
+
+/// Cursor shapes selectable with DECSCUSR (`CSI Ps SP q`)
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum CursorShape {
+    #[default]
+    Block,
+    Underline,
+    Bar,
+}
+
+/// Where the cursor is and how it should be drawn. `x` and `y` are 0-based screen coordinates.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Cursor {
+    pub x: usize,
+    pub y: usize,
+    pub visible: bool,
+    pub shape: CursorShape,
+    pub blinking: bool,
+}
+
+/// Requests from the program that the front end has to carry out
+pub enum TerminalEvent {
+    /// OSC 52 copy, with the base64 payload as it was sent
+    ClipboardStore(String),
+    /// OSC 52 query, answered with the clipboard contents if the policy allows it
+    ClipboardLoad,
+    /// The window title or icon name changed, through OSC 0, 1 or 2 or by popping the title stack
+    Title { title: String, icon_name: String },
+    /// An answer to a query like DSR or DA, to be written back to the program
+    Reply(String),
+}
+
+/// What changed on the screen since the last `completed_render`, so the front end only has to
+/// draw that
+#[derive(Clone)]
+pub struct Damage {
+    /// Columns that changed on each screen row, `None` for rows that didn't
+    pub rows: Vec<Option<Range<usize>>>,
+    /// How many rows the screen moved up, negative if it moved down. Rows that only moved along
+    /// aren't in `rows`, what was drawn for them last frame can be moved the same way.
+    pub scrolled: isize,
+    width: usize,
+}
+
+impl Damage {
+    /// Damage for a `width` by `height` screen where every cell changed
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            rows: vec![Some(0..width); height],
+            scrolled: 0,
+            width,
+        }
+    }
+
+    fn cell(&mut self, x: usize, y: usize) {
+        if let Some(row) = self.rows.get_mut(y) {
+            *row = match row.take() {
+                Some(cols) => Some(cols.start.min(x)..cols.end.max(x + 1)),
+                None => Some(x..x + 1),
+            };
+        }
+    }
+
+    fn row(&mut self, y: usize) {
+        if let Some(row) = self.rows.get_mut(y) {
+            *row = Some(0..self.width);
+        }
+    }
+
+    /// Moves the damage along with the rows when the screen scrolls up by `n` rows, or down if
+    /// `n` is negative. The rows that come in are all new.
+    fn scroll(&mut self, n: isize) {
+        let height = self.rows.len();
+        let count = n.unsigned_abs().min(height);
+        if n > 0 {
+            self.rows.rotate_left(count);
+            for y in height - count..height {
+                self.row(y);
+            }
+        } else {
+            self.rows.rotate_right(count);
+            for y in 0..count {
+                self.row(y);
+            }
+        }
+        self.scrolled = (self.scrolled + n).clamp(-(height as isize), height as isize);
+    }
+
+    fn clear(&mut self) {
+        self.rows.iter_mut().for_each(|row| *row = None);
+        self.scrolled = 0;
+    }
+}
+
+/// The DEC private modes `Terminal` supports, what setting one does and what DECRQM reports
+/// for it both follow from this
+#[derive(Copy, Clone)]
+enum DecMode {
+    /// 9, 1000, 1002 and 1003, resetting any of them turns tracking off
+    MouseTracking(MouseTracking),
+    /// 1006 and 1015
+    MouseEncoding(MouseEncoding),
+    CursorBlinking,
+    CursorVisible,
+    BracketedPaste,
+    SynchronizedOutput,
+}
+
+impl DecMode {
+    fn from_number(mode: usize) -> Option<Self> {
+        Some(match mode {
+            9 => DecMode::MouseTracking(MouseTracking::X10),
+            1000 => DecMode::MouseTracking(MouseTracking::Normal),
+            1002 => DecMode::MouseTracking(MouseTracking::ButtonEvent),
+            1003 => DecMode::MouseTracking(MouseTracking::AnyEvent),
+            1006 => DecMode::MouseEncoding(MouseEncoding::Sgr),
+            1015 => DecMode::MouseEncoding(MouseEncoding::Urxvt),
+            12 => DecMode::CursorBlinking,
+            25 => DecMode::CursorVisible,
+            2004 => DecMode::BracketedPaste,
+            2026 => DecMode::SynchronizedOutput,
+            _ => return None,
+        })
+    }
+}
+
+/// How many titles XTWINOPS 22 keeps, like xterm
+const TITLE_STACK_LIMIT: usize = 10;
+
+/// Lines kept in the scrollback history unless the front end asks for a different limit
+pub const HISTORY_LIMIT: usize = 10_000;
+
 pub struct CharacterCellManager {
-    pub cells: Vec<Vec<CharacterCell>>,
+    /// The screen and the lines scrolled off the top of it
+    pub grid: Grid,
+    /// Total number of lines ever scrolled into history. Lines are addressed by absolute line
+    /// numbers so positions stay put while the screen scrolls, the top row of the screen is
+    /// line `lines_scrolled`.
+    pub lines_scrolled: usize,
+    // Lives here rather than on `Terminal` since the framebuffer is all the front end can see
+    pub modes: TerminalModes,
+    pub hyperlinks: Hyperlinks,
+    pub damage: Damage,
+    // Copy of the `Terminal` cursor, updated by everything that moves or restyles it
+    cursor: Cursor,
+    // Size asked for by the front end, applied by `Terminal` on the next `completed_render`
+    pending_resize: Cell<Option<(usize, usize)>>,
+    events: RefCell<Vec<TerminalEvent>>,
+}
+
+impl CharacterCellManager {
+    pub fn new(grid: Grid) -> Self {
+        let damage = Damage::new(grid.width(), grid.height());
+        Self {
+            grid,
+            lines_scrolled: 0,
+            modes: TerminalModes::default(),
+            hyperlinks: Hyperlinks::default(),
+            damage,
+            cursor: Cursor { x: 0, y: 0, visible: true, shape: CursorShape::Block, blinking: true },
+            pending_resize: Cell::new(None),
+            events: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn cursor(&self) -> Cursor {
+        self.cursor
+    }
+
+    /// Asks the terminal to change its size to `width` columns and `height` rows. The front end
+    /// only gets to see the framebuffer, so the new size takes effect on the next
+    /// `completed_render`.
+    pub fn request_resize(&self, width: usize, height: usize) {
+        self.pending_resize.set(Some((width, height)));
+    }
+
+    /// Returns the events that came up since the last call, oldest first
+    pub fn take_events(&self) -> Vec<TerminalEvent> {
+        self.events.take()
+    }
+
+    /// Absolute line number of the oldest line still in history
+    pub fn first_line(&self) -> usize {
+        self.lines_scrolled - self.grid.history_len()
+    }
+
+    /// Absolute line number of the bottom row of the screen
+    pub fn last_line(&self) -> usize {
+        self.lines_scrolled + self.grid.height() - 1
+    }
+
+    /// Returns the row at absolute line number `line`, whether it's in history or on screen
+    pub fn line(&self, line: usize) -> Option<&Row> {
+        self.grid.line(line.checked_sub(self.first_line())?)
+    }
+
+    /// Whether the text on `line` continues on the next line because it was wrapped
+    pub fn is_wrapped(&self, line: usize) -> bool {
+        self.line(line).and_then(|row| row.last()).is_some_and(|cell| cell.wrap())
+    }
 }
 
 pub struct Terminal {
@@ -44,62 +337,241 @@ pub struct Terminal {
     cell_manager: CharacterCellManager,
 
     // Current settings applied to any printed text
-    curr_fg_col: Color,
-    curr_bg_col: Color,
+    curr_fg_col: CellColor,
+    curr_bg_col: CellColor,
     curr_is_bold: bool,
     curr_inverse: bool,
+    curr_hyperlink: Option<HyperlinkId>,
 
-    // TODO: Move to renderer since it's not terminal logic
     cursor_visible: bool,
+    cursor_shape: CursorShape,
+    cursor_blinking: bool,
+
+    title: String,
+    icon_name: String,
+    // Saved `(title, icon_name)` pairs
+    title_stack: Vec<(String, String)>,
 }
 
 impl Terminal {
     pub fn new(width: usize, height: usize) -> Self {
+        Self::with_history_limit(width, height, HISTORY_LIMIT)
+    }
+
+    /// A terminal keeping at most `history_limit` lines of scrollback
+    pub fn with_history_limit(width: usize, height: usize, history_limit: usize) -> Self {
         Self {
             x: 1,
             y: 1,
             width,
             height,
-            cell_manager: CharacterCellManager { cells: vec![vec![CharacterCell { ch: ' ', fg_col: FG_COL, bg_col: BG_COL, bold: false, inverse: false, dirty: true, }; width]; height] },
-            curr_fg_col: FG_COL,
-            curr_bg_col: BG_COL,
+            cell_manager: CharacterCellManager::new(Grid::new(width, height, history_limit, CharacterCell::new(' ', CellColor::FG, CellColor::BG, Flags::default()))),
+            curr_fg_col: CellColor::FG,
+            curr_bg_col: CellColor::BG,
             curr_is_bold: false,
             curr_inverse: false,
+            curr_hyperlink: None,
             cursor_visible: true,
+            cursor_shape: CursorShape::Block,
+            cursor_blinking: true,
+            title: String::new(),
+            icon_name: String::new(),
+            title_stack: Vec::new(),
         }
     }
 
     fn reset(&mut self) {
-        self.curr_fg_col = FG_COL;
-        self.curr_bg_col = BG_COL;
+        self.curr_fg_col = CellColor::FG;
+        self.curr_bg_col = CellColor::BG;
         self.curr_is_bold = false;
         self.curr_inverse = false;
-        self.cursor_visible = true;
     }
 
-    fn set_cell(&mut self, x: usize, y: usize, ch: char) {
+    /// Only printed characters are part of a hyperlink, erased cells pass `None`
+    fn set_cell(&mut self, x: usize, y: usize, ch: char, hyperlink: Option<HyperlinkId>) {
         if x > self.width || y > self.height || x == 0 || y == 0 {
             eprintln!("Trying to print outside the screen to position {}:{}, this is an error", x, y);
             return;
         }
-        self.cell_manager.cells[y-1][x-1].ch = ch;
-        self.cell_manager.cells[y-1][x-1].fg_col = self.curr_fg_col;
-        self.cell_manager.cells[y-1][x-1].bg_col = self.curr_bg_col;
-        self.cell_manager.cells[y-1][x-1].bold = self.curr_is_bold;
-        self.cell_manager.cells[y-1][x-1].inverse = self.curr_inverse;
-        self.cell_manager.cells[y-1][x-1].dirty = true;
+        let mut cell = CharacterCell::new(ch, self.curr_fg_col, self.curr_bg_col, self.curr_flags());
+        cell.set_hyperlink(hyperlink);
+        self.cell_manager.grid[y-1][x-1] = cell;
+        self.cell_manager.damage.cell(x-1, y-1);
     }
 
-    pub fn default_cell(&self) -> CharacterCell {
-        CharacterCell {
-            ch: ' ',
-            fg_col: self.curr_fg_col,
-            bg_col: self.curr_bg_col,
-            bold: self.curr_is_bold,
-            inverse: self.curr_inverse,
-            dirty: true,
+    /// Moves the cursor down a row, scrolling the screen up when it's on the bottom row
+    fn line_feed(&mut self) {
+        if self.y >= self.height as isize {
+            self.scroll_up(1);
+            self.y = self.height as isize;
+        } else {
+            self.y += 1;
+        }
+        self.update_cursor();
+    }
+
+    fn set_dec_private_mode(&mut self, mode: usize, set: bool) {
+        let modes = &mut self.cell_manager.modes;
+        match DecMode::from_number(mode) {
+            Some(DecMode::MouseTracking(tracking)) => {
+                modes.mouse_tracking = if set { tracking } else { MouseTracking::Off };
+            }
+            Some(DecMode::MouseEncoding(encoding)) => {
+                if set {
+                    modes.mouse_encoding = encoding;
+                } else if modes.mouse_encoding == encoding {
+                    modes.mouse_encoding = MouseEncoding::Default;
+                }
+            }
+            Some(DecMode::CursorBlinking) => self.cursor_blinking = set,
+            Some(DecMode::CursorVisible) => self.cursor_visible = set,
+            Some(DecMode::BracketedPaste) => modes.bracketed_paste = set,
+            Some(DecMode::SynchronizedOutput) => modes.synchronized_output = set,
+            None => eprintln!("Warning: Unknown DEC private mode: {mode}"),
+        }
+        self.update_cursor();
+    }
+
+    /// Whether a DEC private mode is set, `None` for modes that aren't supported
+    fn dec_private_mode(&self, mode: usize) -> Option<bool> {
+        let modes = &self.cell_manager.modes;
+        Some(match DecMode::from_number(mode)? {
+            DecMode::MouseTracking(tracking) => modes.mouse_tracking == tracking,
+            DecMode::MouseEncoding(encoding) => modes.mouse_encoding == encoding,
+            DecMode::CursorBlinking => self.cursor_blinking,
+            DecMode::CursorVisible => self.cursor_visible,
+            DecMode::BracketedPaste => modes.bracketed_paste,
+            DecMode::SynchronizedOutput => modes.synchronized_output,
+        })
+    }
+
+    /// DECSCUSR, `CSI Ps SP q`
+    fn set_cursor_style(&mut self, n: usize) {
+        let (shape, blinking) = match n {
+            0 | 1 => (CursorShape::Block, true),
+            2 => (CursorShape::Block, false),
+            3 => (CursorShape::Underline, true),
+            4 => (CursorShape::Underline, false),
+            5 => (CursorShape::Bar, true),
+            6 => (CursorShape::Bar, false),
+            _ => {
+                eprintln!("Warning: Unknown cursor style: {n}");
+                return;
+            }
+        };
+        self.cursor_shape = shape;
+        self.cursor_blinking = blinking;
+        self.update_cursor();
+    }
+
+    fn set_title_and_icon(&mut self, title: Option<String>, icon_name: Option<String>) {
+        if let Some(title) = title {
+            self.title = title;
+        }
+        if let Some(icon_name) = icon_name {
+            self.icon_name = icon_name;
+        }
+        self.cell_manager.events.get_mut().push(TerminalEvent::Title {
+            title: self.title.clone(),
+            icon_name: self.icon_name.clone(),
+        });
+    }
+
+    /// XTWINOPS, `CSI Ps ; Ps ; Ps t`. Only 22 and 23, saving and restoring the title, are
+    /// supported since the rest move and resize the window.
+    fn window_op(&mut self, params: &[usize]) {
+        // 0 is both, 1 the icon name and 2 the title
+        let which = params.get(1).copied().unwrap_or(0);
+        let (title, icon) = (which != 1, which != 2);
+        match params.first() {
+            Some(22) => {
+                if self.title_stack.len() == TITLE_STACK_LIMIT {
+                    self.title_stack.remove(0);
+                }
+                self.title_stack.push((self.title.clone(), self.icon_name.clone()));
+            }
+            Some(23) => {
+                if let Some((saved_title, saved_icon)) = self.title_stack.pop() {
+                    self.set_title_and_icon(Some(saved_title).filter(|_| title), Some(saved_icon).filter(|_| icon));
+                }
+            }
+            _ => eprintln!("Warning: Unsupported window operation: {params:?}"),
+        }
+    }
+
+    fn cursor(&self) -> Cursor {
+        // x can sit one past the last column while a wrap is pending
+        Cursor {
+            x: (self.x.max(1) as usize - 1).min(self.width - 1),
+            y: (self.y.max(1) as usize - 1).min(self.height - 1),
+            visible: self.cursor_visible,
+            shape: self.cursor_shape,
+            blinking: self.cursor_blinking,
         }
     }
+
+    /// Frees the hyperlinks no cell uses anymore, because their lines were overwritten or
+    /// dropped from history
+    fn collect_hyperlinks(&mut self) {
+        let grid = &self.cell_manager.grid;
+        let mut used: HashSet<HyperlinkId> = self.curr_hyperlink.into_iter().collect();
+        for i in 0..grid.history_len() + grid.height() {
+            used.extend(grid.line(i).into_iter().flatten().filter_map(|cell| cell.hyperlink()));
+        }
+        self.cell_manager.hyperlinks.retain(&used);
+    }
+
+    /// Copies the cursor into the framebuffer, the front end can't ask `Terminal` for it
+    fn update_cursor(&mut self) {
+        self.cell_manager.cursor = self.cursor();
+    }
+
+    /// Changes the screen size. Rows that don't fit above the cursor anymore are moved into
+    /// history and rows below it are dropped, lines aren't reflowed.
+    fn resize(&mut self, width: usize, height: usize) {
+        let (width, height) = (width.max(1), height.max(1));
+        let overflow = (self.y.max(1) as usize).saturating_sub(height);
+        let blank = CharacterCell::new(' ', CellColor::FG, CellColor::BG, Flags::default());
+        self.cell_manager.grid.resize(width, height, overflow, blank);
+        self.cell_manager.lines_scrolled += overflow;
+        self.cell_manager.damage = Damage::new(width, height);
+
+        self.width = width;
+        self.height = height;
+        self.x = self.x.clamp(1, width as isize);
+        self.y = (self.y - overflow as isize).clamp(1, height as isize);
+        self.update_cursor();
+    }
+
+    pub fn default_cell(&self) -> CharacterCell {
+        CharacterCell::new(' ', self.curr_fg_col, self.curr_bg_col, self.curr_flags())
+    }
+
+    fn curr_flags(&self) -> Flags {
+        let mut flags = Flags::default();
+        flags.set(Flags::BOLD, self.curr_is_bold);
+        flags.set(Flags::INVERSE, self.curr_inverse);
+        flags
+    }
+}
+
+/// The body of an OSC sequence, without the introducer and terminator if they are included
+fn osc_body(s: &str) -> &str {
+    let s = s.trim_start_matches('\x1b').trim_start_matches(']');
+    s.trim_end_matches('\x07').trim_end_matches("\x1b\\")
+}
+
+/// Splits a DEC private mode sequence like `ESC[?2004h` into its mode numbers and whether they
+/// are being set (`h`) or reset (`l`).
+fn parse_dec_private_mode(s: &str) -> Option<(Vec<usize>, bool)> {
+    let s = s.trim_start_matches('\x1b').trim_start_matches('[').strip_prefix('?')?;
+    let set = match s.chars().last()? {
+        'h' => true,
+        'l' => false,
+        _ => return None,
+    };
+    let modes = s[..s.len()-1].split(';').filter_map(|m| m.parse().ok()).collect();
+    Some((modes, set))
 }
 
 impl TermInterface<CharacterCellManager> for Terminal {
@@ -108,10 +580,10 @@ impl TermInterface<CharacterCellManager> for Terminal {
     }
 
     fn completed_render(&mut self) {
-        for row in self.cell_manager.cells.iter_mut() {
-            for cell in row.iter_mut() {
-                cell.dirty = false;
-            }
+        self.cell_manager.damage.clear();
+        // Done after clearing the damage so the next frame redraws every cell
+        if let Some((width, height)) = self.cell_manager.pending_resize.take() {
+            self.resize(width, height);
         }
     }
 
@@ -120,7 +592,7 @@ impl TermInterface<CharacterCellManager> for Terminal {
             match ch {
                 '\n' => {
                     self.goto_x(1);
-                    self.move_y(1);
+                    self.line_feed();
                 }
                 '\r' => {
                     self.goto_x(1);
@@ -130,10 +602,11 @@ impl TermInterface<CharacterCellManager> for Terminal {
                         return;
                     }
                     self.x -= 1;
-                    self.set_cell(self.x as usize, self.y as usize, ' ');
+                    self.update_cursor();
+                    self.set_cell(self.x as usize, self.y as usize, ' ', None);
                 }
                 _ => {
-                    self.set_cell(self.x as usize, self.y as usize, ch);
+                    self.set_cell(self.x as usize, self.y as usize, ch, self.curr_hyperlink);
                     self.move_x(1);
                 }
             }
@@ -141,31 +614,31 @@ impl TermInterface<CharacterCellManager> for Terminal {
     }
 
     fn goto_x(&mut self, x: usize) {
-        self.x = x as isize;
+        self.x = x.clamp(1, self.width) as isize;
+        self.update_cursor();
     }
 
     fn goto_y(&mut self, y: usize) {
-        self.y = y as isize;
+        self.y = y.clamp(1, self.height) as isize;
+        self.update_cursor();
     }
 
     fn move_x(&mut self, x: isize) {
         // TODO: Should this overflow to next line or stop at the end?
-        // TODO: Handle negative values
-        self.x = self.x.wrapping_add(x);
+        self.x = self.x.saturating_add(x).max(1);
         if self.x >= self.width as isize {
+            if let Some(cell) = self.cell_manager.grid.get_mut((self.y - 1) as usize).and_then(|row| row.last_mut()) {
+                cell.set_wrap(true);
+            }
             self.x = 1;
-            self.move_y(1);
+            self.line_feed();
         }
+        self.update_cursor();
     }
 
     fn move_y(&mut self, y: isize) {
-        // TODO: Handle line scrolling
-        // TODO: Handle negative values
-        self.y = self.y.wrapping_add(y);
-        if self.y > self.height as isize {
-            self.scroll_up(self.y.wrapping_sub(self.height as isize) as usize);
-            self.y = self.height as isize;
-        }
+        self.y = self.y.saturating_add(y).clamp(1, self.height as isize);
+        self.update_cursor();
     }
 
     fn erase_in_display(&mut self, n: usize) {
@@ -196,9 +669,12 @@ impl TermInterface<CharacterCellManager> for Terminal {
                     self.y = y as isize;
                     self.erase_in_line(2);
                 }
+                if n == 3 {
+                    self.cell_manager.grid.clear_history();
+                }
             }
             _ => {
-                panic!("Unknown ED {}", n)
+                eprintln!("Warning: Unknown ED {n}");
             }
         }
         self.y = temp_y;
@@ -208,17 +684,17 @@ impl TermInterface<CharacterCellManager> for Terminal {
         match n {
             0 => {
                 for x in self.x..(self.width as isize) {
-                    self.set_cell(x as usize, self.y as usize, ' ');
+                    self.set_cell(x as usize, self.y as usize, ' ', None);
                 }
             }
             1 => {
                 for x in 1..=self.x {
-                    self.set_cell(x as usize, self.y as usize, ' ');
+                    self.set_cell(x as usize, self.y as usize, ' ', None);
                 }
             }
             2 => {
                 for x in 1..=self.width {
-                    self.set_cell(x, self.y as usize, ' ');
+                    self.set_cell(x, self.y as usize, ' ', None);
                 }
             }
             _ => {}
@@ -226,43 +702,30 @@ impl TermInterface<CharacterCellManager> for Terminal {
     }
 
     fn scroll_up(&mut self, n: usize) {
-        println!("Scrolling up {}", n);
-        for _ in 0..n {
-            self.cell_manager.cells.remove(0);
-            self.cell_manager.cells.push(vec![self.default_cell(); self.width]);
-        }
-
-        for lines in self.cell_manager.cells.iter_mut() {
-            for cell in lines.iter_mut() {
-                cell.dirty = true;
-            }
-        }
+        // Past a screenful there is nothing left but blank lines, and the count can be huge
+        let n = n.min(self.height);
+        let blank = self.default_cell();
+        self.cell_manager.lines_scrolled += self.cell_manager.grid.scroll_up(0..self.height, n, blank);
+        self.cell_manager.damage.scroll(n as isize);
     }
 
     fn scroll_down(&mut self, n: usize) {
-        println!("Scrolling down {}", n);
-        for _ in 0..n {
-            self.cell_manager.cells.pop();
-            self.cell_manager.cells.insert(0, vec![self.default_cell(); self.width]);
-        }
-
-        for lines in self.cell_manager.cells.iter_mut() {
-            for cell in lines.iter_mut() {
-                cell.dirty = true;
-            }
-        }
+        let n = n.min(self.height);
+        let blank = self.default_cell();
+        self.cell_manager.grid.scroll_down(0..self.height, n, blank);
+        self.cell_manager.damage.scroll(-(n as isize));
     }
 
     fn il(&mut self, n: usize) {
         // Moves current line and lines below down by `n` lines, and clears the current line
-        let saved_row = self.cell_manager.cells[self.y as usize-1].clone();
-        self.erase_in_line(2);
-        let empty_row = self.cell_manager.cells[self.y as usize-1].clone();
-        self.cell_manager.cells[self.y as usize-1] = saved_row;
-
-        for _ in 0..n {
-            self.cell_manager.cells.insert(self.y as usize - 1, empty_row.clone());
-            self.cell_manager.cells.pop();
+        if self.y < 1 || self.y > self.height as isize {
+            return;
+        }
+        let n = n.min(self.height + 1 - self.y as usize);
+        let blank = self.default_cell();
+        self.cell_manager.grid.scroll_down(self.y as usize - 1..self.height, n, blank);
+        for y in self.y as usize - 1..self.height {
+            self.cell_manager.damage.row(y);
         }
     }
 
@@ -274,85 +737,54 @@ impl TermInterface<CharacterCellManager> for Terminal {
                 0 => self.reset(),
                 1 => self.curr_is_bold = true,
                 30..=37 => {
-                    if self.curr_is_bold {
-                        self.curr_fg_col = BOLD_COL_ARR[mode - 30];
-                    } else {
-                        self.curr_fg_col = COL_ARR[mode - 30];
-                    }
+                    // Bold text gets the bright colors
+                    let bright = if self.curr_is_bold { 8 } else { 0 };
+                    self.curr_fg_col = CellColor::Indexed((mode - 30 + bright) as u8);
                 }
-                40..=47 => self.curr_bg_col = COL_ARR[mode - 40],
+                40..=47 => self.curr_bg_col = CellColor::Indexed((mode - 40) as u8),
+                // A color that is cut short or of an unknown kind ends the sequence, like in xterm
                 38 | 48 => {
-                    match iter.next().expect("Missing color type") {
-                        2 => {
-                            let r = iter.next().expect("No red specified");
-                            let g = iter.next().expect("No green specified");
-                            let b = iter.next().expect("No blue specified");
-                            if mode == 38 {
-                                self.curr_fg_col = Color::RGB(*r as u8, *g as u8, *b as u8);
-                            } else {
-                                self.curr_bg_col = Color::RGB(*r as u8, *g as u8, *b as u8);
+                    let color = match iter.next() {
+                        Some(2) => {
+                            match (iter.next(), iter.next(), iter.next()) {
+                                (Some(r), Some(g), Some(b)) => CellColor::Rgb(*r as u8, *g as u8, *b as u8),
+                                _ => {
+                                    eprintln!("Warning: SGR {} is missing a color component", mode);
+                                    return;
+                                }
                             }
                         }
-                        5 => {
-                            let mut c = *iter.next().unwrap();
-                            match c {
-                                0..=7 => {
-                                    if mode == 38 {
-                                        self.curr_fg_col = COL_ARR[c]
-                                    } else {
-                                        self.curr_bg_col = COL_ARR[c]
-                                    }
+                        Some(5) => {
+                            match iter.next() {
+                                Some(&mut c) if c <= 255 => CellColor::Indexed(c as u8),
+                                Some(c) => {
+                                    eprintln!("Warning: SGR {} color index out of range: {}", mode, c);
+                                    return;
                                 }
-                                8..=15 => {
-                                    if mode == 38 {
-                                        self.curr_fg_col = BOLD_COL_ARR[c-8]
-                                    } else {
-                                        self.curr_bg_col = BOLD_COL_ARR[c-8]
-                                    }
-                                }
-                                16..=231 => {
-                                    c -= 16;
-                                    let index_r = c / 36;
-                                    let index_g = (c % 36) / 6;
-                                    let index_b = c % 6;
-
-                                    let r = 55 + if index_r > 0 {index_r*40} else {0} as u8;
-                                    let g = 55 + if index_g > 0 {index_g*40} else {0} as u8;
-                                    let b = 55 + if index_b > 0 {index_b*40} else {0} as u8;
-
-                                    if mode == 38 {
-                                        self.curr_fg_col = Color::RGB(r,g,b);
-                                        print!("FG ");
-                                    } else {
-                                        self.curr_bg_col = Color::RGB(r,g,b);
-                                        print!("BG ");
-                                    }
-                                    print!("\x1b[38;2;{};{};{}m", self.curr_fg_col.r, self.curr_fg_col.g, self.curr_fg_col.b);
-                                    print!("\x1b[48;2;{};{};{}m", self.curr_bg_col.r, self.curr_bg_col.g, self.curr_bg_col.b);
-                                    //print!("\x1b[{};2;{};{};{}m", a, r, g, b);
-                                    println!("COLOR\x1b[m: {:x}, {:x}, {:x}", r, g, b)
-                                }
-                                _ => {
-                                    let c = (0x8+(c-232)*0xA) as u8;
-                                    if mode == 38 {
-                                        self.curr_fg_col = Color::RGB(c,c,c);
-                                    } else {
-                                        self.curr_bg_col = Color::RGB(c,c,c);
-                                    }
-                                    println!("RGB COLOR: {:x}, {:x}, {:x}", c, c, c)
+                                None => {
+                                    eprintln!("Warning: SGR {} is missing a color index", mode);
+                                    return;
                                 }
                             }
                         }
-                        _ => panic!("Wrong color mode")
+                        color_type => {
+                            eprintln!("Warning: Unknown SGR {} color type: {:?}", mode, color_type);
+                            return;
+                        }
+                    };
+                    if mode == 38 {
+                        self.curr_fg_col = color;
+                    } else {
+                        self.curr_bg_col = color;
                     }
                 }
-                39 => self.curr_fg_col = FG_COL,
-                49 => self.curr_bg_col = BG_COL,
+                39 => self.curr_fg_col = CellColor::FG,
+                49 => self.curr_bg_col = CellColor::BG,
                 90..=97 => {
-                    self.curr_fg_col = BOLD_COL_ARR[mode - 90];
+                    self.curr_fg_col = CellColor::Indexed((mode - 90 + 8) as u8);
                 }
                 100..=107 => {
-                    self.curr_fg_col = BOLD_COL_ARR[mode - 100];
+                    self.curr_fg_col = CellColor::Indexed((mode - 100 + 8) as u8);
                 }
                 _ => {
                     eprintln!("Unknown SGR: {}", mode);
@@ -366,24 +798,92 @@ impl TermInterface<CharacterCellManager> for Terminal {
     }
 
     fn decslrm(&mut self, left: usize, right: usize) {
-        todo!()
+        eprintln!("Warning: Left and right margins are not supported: {left};{right}");
     }
 
-    fn dectcem(&mut self, show: bool) { self.cursor_visible = show; }
+    fn dectcem(&mut self, show: bool) {
+        self.cursor_visible = show;
+        self.update_cursor();
+    }
 
+    /// DSR 6, the cursor position report
     fn device_status_report(&mut self) -> (usize, usize) {
-        todo!()
+        let cursor = self.cursor();
+        let reply = format!("\x1b[{};{}R", cursor.y + 1, cursor.x + 1);
+        self.cell_manager.events.get_mut().push(TerminalEvent::Reply(reply));
+        (cursor.x + 1, cursor.y + 1)
     }
 
     fn unknown_csi(&mut self, s: String) {
+        if let Some((modes, set)) = parse_dec_private_mode(&s) {
+            for mode in modes {
+                self.set_dec_private_mode(mode, set);
+            }
+            return;
+        }
+        let params = s.trim_start_matches('\x1b').trim_start_matches('[');
+        if let Some(n) = params.strip_suffix(" q") {
+            self.set_cursor_style(n.parse().unwrap_or(0));
+            return;
+        }
+        if let Some(params) = params.strip_suffix('t') {
+            let params: Vec<usize> = params.split(';').map(|n| n.parse().unwrap_or(0)).collect();
+            self.window_op(&params);
+            return;
+        }
+        // DECRQM, programs use it to find out if synchronized output is supported
+        if let Some(mode) = params.strip_prefix('?').and_then(|p| p.strip_suffix("$p")) {
+            let mode = mode.parse().unwrap_or(0);
+            let state = match self.dec_private_mode(mode) {
+                Some(true) => 1,
+                Some(false) => 2,
+                None => 0,
+            };
+            let reply = format!("\x1b[?{mode};{state}$y");
+            self.cell_manager.events.get_mut().push(TerminalEvent::Reply(reply));
+            return;
+        }
+        // Status report and device attributes, answered as a VT220 with color
+        let reply = match params {
+            "5n" => Some("\x1b[0n"),
+            "c" | "0c" => Some("\x1b[?62;22c"),
+            ">c" | ">0c" => Some("\x1b[>1;10;0c"),
+            _ => None,
+        };
+        if let Some(reply) = reply {
+            self.cell_manager.events.get_mut().push(TerminalEvent::Reply(reply.to_string()));
+            return;
+        }
         eprintln!("Warning: Unknown CSI code: {s:?}")
     }
 
+    /// OSC 0 and 2, rust_ansi doesn't tell them apart so both only set the title
     fn set_title(&mut self, title: String) {
-        println!("Set title to: {:?}", title);
+        self.set_title_and_icon(Some(title), None);
     }
 
     fn unknown_osc(&mut self, s: String) {
+        let body = osc_body(&s);
+        if let Some(link) = hyperlink::parse_osc8(body) {
+            if self.cell_manager.hyperlinks.should_collect() {
+                self.collect_hyperlinks();
+            }
+            self.curr_hyperlink = link.and_then(|link| self.cell_manager.hyperlinks.intern(link));
+            return;
+        }
+        // OSC 0 and 2 go to `set_title` instead
+        if let Some(name) = body.strip_prefix("1;") {
+            return self.set_title_and_icon(None, Some(name.to_string()));
+        }
+        // `52;selection;data`, only the clipboard is supported so the selection is ignored
+        if let Some((_, data)) = body.strip_prefix("52;").and_then(|s| s.split_once(';')) {
+            let event = match data {
+                "?" => TerminalEvent::ClipboardLoad,
+                _ => TerminalEvent::ClipboardStore(data.to_string()),
+            };
+            self.cell_manager.events.get_mut().push(event);
+            return;
+        }
         eprintln!("Warning: Unknown OSC code: {s:?}")
     }
 
@@ -1,6 +1,6 @@
 extern crate sdl2;
 
-use sdl2::pixels::Color;
+use rust_terminal::color::{Color, BG_COL, BOLD_COL_ARR, BOLD_FG_COL, COL_ARR, FG_COL};
 use sdl2::render::WindowCanvas;
 use crate::renderer::TermRenderer;
 use sdl2::rect::Rect;
@@ -60,34 +60,9 @@ pub struct TextArea {
     bottom_margin: usize,
 }
 
-pub const COL_ARR: [Color; 8] = [
-    Color {r: 0x23, g: 0x26, b: 0x27, a: 0},     // BLACK
-    Color {r: 0xed, g: 0x15, b: 0x15, a: 0},   // RED
-    Color {r: 0x11, g: 0xd1, b: 0x16, a: 0},   // GREEN
-    Color {r: 0xf6, g: 0x74, b: 0x00, a: 0}, // YELLOW
-    Color {r: 0x1d, g: 0x99, b: 0xf3, a: 0},   // BLUE
-    Color {r: 0x9b, g: 0x59, b: 0xb6, a: 0}, // MAGENTA
-    Color {r: 0x1a, g: 0xbc, b: 0x9c, a: 0}, // CYAN
-    Color {r: 0xfc, g: 0xfc, b: 0xfc, a: 0}, // WHITE
-];
-pub const BOLD_COL_ARR: [Color; 8] = [
-    Color {r: 0x7f, g: 0x8c, b: 0x8d, a: 0},// BLACK
-    Color {r: 0xc0, g: 0x39, b: 0x2b, a: 0},   // RED
-    Color {r: 0x1c, g: 0xdc, b: 0x9a, a: 0},   // GREEN
-    Color {r: 0xfd, g: 0xbc, b: 0x4b, a: 0}, // YELLOW
-    Color {r: 0x3d, g: 0xae, b: 0xe9, a: 0},   // BLUE
-    Color {r: 0x8e, g: 0x44, b: 0xad, a: 0}, // MAGENTA
-    Color {r: 0x16, g: 0xa0, b: 0x85, a: 0}, // CYAN
-    Color {r: 0xff, g: 0xff, b: 0xff, a: 0}, // WHITE
-];
-
-pub const BG_COL: Color = COL_ARR[0];
 #[allow(dead_code)]
 const BOLD_BG_COL: Color = Color {r: 0x00, g: 0x00, b: 0x00, a: 0};
 
-pub const FG_COL: Color = COL_ARR[7];
-pub const BOLD_FG_COL: Color = BOLD_COL_ARR[7];
-
 impl TextArea {
     pub fn new(width: usize, height: usize, font_width: u32, font_height: u32) -> Self {
         let mut ta = Self {
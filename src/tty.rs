@@ -1,8 +1,25 @@
+use nix::errno::Errno;
 use nix::pty::ForkptyResult;
 use nix::unistd::{ForkResult, read, write};
 use std::ffi::CString;
+use std::os::unix::io::RawFd;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::cell::Cell;
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError};
 use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
 
+/// Most output handed over at once, so a flood still gets shown while it goes on
+const MAX_BATCH: usize = 1 << 16;
+/// Batches the reader thread can get ahead of the front end. After that it waits, and once the
+/// pty buffer is full so does the program.
+const QUEUED_BATCHES: usize = 4;
+/// Most output `PtyReader::take` returns, so a backlog is parsed a bit at a time between frames
+const MAX_TAKE: usize = QUEUED_BATCHES * MAX_BATCH;
+/// How long a write waits for the program to make room in the pty before giving up
+const WRITE_TIMEOUT_MS: i32 = 2000;
+
 pub struct ForkPTY {
     fork_res: Option<ForkptyResult>,
 }
@@ -62,6 +79,7 @@ impl ForkPTY {
         };
     } /* pub fn open */
 
+    #[allow(clippy::result_unit_err)]
     pub fn read(&self) -> Result<([u8; 4096], usize), ()> {
         let mut t: [u8; 4096] = [0; 4096];
         if self.fork_res.is_none() {
@@ -96,7 +114,202 @@ impl ForkPTY {
         }
     }
 
-    pub fn write(&self, s: String) -> usize {
-        write(self.fork_res.unwrap().master, s.as_bytes()).unwrap()
+    /// Like `write_bytes`, for input where all that can be done about a failure is report it
+    pub fn write(&self, s: String) {
+        if let Err(e) = self.write_bytes(s.as_bytes()) {
+            eprintln!("Warning: Could not write to the terminal: {e}");
+        }
+    }
+
+    /// Sends all of `bytes` to the program. The master is non-blocking, so once the pty buffer
+    /// is full this waits for the program to read some of it, and fails with `EAGAIN` if it
+    /// doesn't within `WRITE_TIMEOUT_MS`.
+    pub fn write_bytes(&self, mut bytes: &[u8]) -> nix::Result<()> {
+        let fd = match self.fork_res {
+            Some(fork_res) => fork_res.master,
+            None => return Ok(()),
+        };
+        while !bytes.is_empty() {
+            match write(fd, bytes) {
+                Ok(n) => bytes = &bytes[n..],
+                Err(e) if e.as_errno() == Some(Errno::EINTR) => {}
+                Err(e) if e.as_errno() == Some(Errno::EAGAIN) => {
+                    let mut pollfd = libc::pollfd { fd, events: libc::POLLOUT, revents: 0 };
+                    match unsafe { libc::poll(&mut pollfd, 1, WRITE_TIMEOUT_MS) } {
+                        0 => return Err(e),
+                        n if n < 0 && Errno::last() != Errno::EINTR => return Err(nix::Error::last()),
+                        _ => {}
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Tells the program the terminal is now `width` columns by `height` rows, it gets a SIGWINCH
+    pub fn resize(&self, width: u16, height: u16) {
+        let ws = libc::winsize {
+            ws_row: height,
+            ws_col: width,
+            ws_xpixel: 0,
+            ws_ypixel: 0
+        };
+        if let Some(fork_res) = self.fork_res {
+            unsafe {libc::ioctl(fork_res.master, libc::TIOCSWINSZ, &ws);}
+        }
+    }
+
+    /// Starts a thread that sleeps until the program prints something and passes the output on
+    /// in batches. `wake` is called from that thread when output arrives and everything before
+    /// it was already taken, so a front end can sleep until then as well.
+    pub fn spawn_reader(&self, wake: impl Fn() + Send + 'static) -> PtyReader {
+        let (sender, receiver) = mpsc::sync_channel(QUEUED_BATCHES);
+        let pending = Arc::new(AtomicBool::new(false));
+        if let Some(fork_res) = self.fork_res {
+            let pending = pending.clone();
+            std::thread::spawn(move || {
+                read_loop(fork_res.master, &sender, &pending, &wake);
+                // Dropping the sender tells the front end the program is gone
+                drop(sender);
+                wake();
+            });
+        }
+        PtyReader { receiver, pending, more: Cell::new(false) }
+    }
+
+    /// Working directory of the shell, used to resolve relative paths it printed
+    pub fn cwd(&self) -> Option<PathBuf> {
+        match self.fork_res?.fork_result {
+            ForkResult::Parent { child } => std::fs::read_link(format!("/proc/{child}/cwd")).ok(),
+            ForkResult::Child => None,
+        }
+    }
+}
+
+/// Output read by the thread `ForkPTY::spawn_reader` started
+pub struct PtyReader {
+    receiver: Receiver<Vec<u8>>,
+    // Set while output is waiting, so the reader only wakes the front end once per batch
+    pending: Arc<AtomicBool>,
+    // Set when `take` stopped at `MAX_TAKE`
+    more: Cell<bool>,
+}
+
+impl PtyReader {
+    /// What was read since the last call, up to `MAX_TAKE` bytes and empty if nothing was.
+    /// `None` once the program has exited and all of its output was taken.
+    pub fn take(&self) -> Option<Vec<u8>> {
+        // Cleared first so output arriving while this drains wakes the front end again
+        self.pending.store(false, Ordering::SeqCst);
+        self.more.set(false);
+        let mut res = Vec::new();
+        loop {
+            if res.len() >= MAX_TAKE {
+                self.more.set(true);
+                return Some(res);
+            }
+            match self.receiver.try_recv() {
+                Ok(batch) => res.extend_from_slice(&batch),
+                Err(TryRecvError::Empty) => return Some(res),
+                Err(TryRecvError::Disconnected) if res.is_empty() => return None,
+                Err(TryRecvError::Disconnected) => return Some(res),
+            }
+        }
     }
-}
\ No newline at end of file
+
+    /// Whether the last `take` left output behind. The front end isn't woken for it, so it
+    /// should take again soon instead of sleeping.
+    pub fn has_more(&self) -> bool {
+        self.more.get()
+    }
+}
+
+/// Turns pty output into text. A UTF-8 sequence cut off at the end of one read is kept for the
+/// next, invalid bytes become U+FFFD the way `String::from_utf8_lossy` replaces them.
+#[derive(Default)]
+pub struct Utf8Decoder {
+    partial: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub fn decode(&mut self, bytes: &[u8]) -> String {
+        let joined;
+        let mut rest = if self.partial.is_empty() {
+            bytes
+        } else {
+            joined = [std::mem::take(&mut self.partial).as_slice(), bytes].concat();
+            joined.as_slice()
+        };
+        let mut res = String::with_capacity(rest.len());
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    res.push_str(text);
+                    return res;
+                }
+                Err(err) => {
+                    let (valid, invalid) = rest.split_at(err.valid_up_to());
+                    res.push_str(std::str::from_utf8(valid).unwrap());
+                    match err.error_len() {
+                        Some(len) => {
+                            res.push(char::REPLACEMENT_CHARACTER);
+                            rest = &invalid[len..];
+                        }
+                        // The sequence goes on in the next read
+                        None => {
+                            self.partial = invalid.to_vec();
+                            return res;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Blocks until `fd` can be read, then reads everything there is, waiting while the front end
+/// is `QUEUED_BATCHES` behind. Returns when the program exits or nobody is listening anymore.
+fn read_loop(fd: RawFd, sender: &SyncSender<Vec<u8>>, pending: &AtomicBool, wake: &impl Fn()) {
+    let mut buf = [0u8; 4096];
+    loop {
+        let mut pollfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+        if unsafe { libc::poll(&mut pollfd, 1, -1) } < 0 {
+            if Errno::last() == Errno::EINTR {
+                continue;
+            }
+            return;
+        }
+
+        let mut batch = Vec::new();
+        let mut exited = false;
+        while batch.len() < MAX_BATCH {
+            match read(fd, &mut buf) {
+                Ok(0) => {
+                    exited = true;
+                    break;
+                }
+                Ok(n) => batch.extend_from_slice(&buf[..n]),
+                Err(e) if e.as_errno() == Some(Errno::EINTR) => {}
+                Err(e) if e.as_errno() == Some(Errno::EAGAIN) => break,
+                // The master gives EIO once the program and everything it started are gone
+                Err(_) => {
+                    exited = true;
+                    break;
+                }
+            }
+        }
+
+        if !batch.is_empty() {
+            if sender.send(batch).is_err() {
+                return;
+            }
+            if !pending.swap(true, Ordering::SeqCst) {
+                wake();
+            }
+        }
+        if exited {
+            return;
+        }
+    }
+}
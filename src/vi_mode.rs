@@ -0,0 +1,219 @@
+use sdl2::keyboard::Keycode;
+use rust_terminal::selection::{Point, Selection, SelectionKind};
+use rust_terminal::term::CharacterCellManager;
+
+pub enum ViAction {
+    None,
+    /// Copy the text and leave copy mode
+    Yank(String),
+    /// Open the search prompt, `search_backwards` says which way it goes
+    Search,
+    /// Jump to the next match of the last search, or the previous one if `reverse`
+    SearchNext { reverse: bool },
+    Exit,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum CharClass {
+    Blank,
+    Word,
+    Punctuation,
+}
+
+/// Keyboard driven copy mode with vi motions. The cursor moves over the screen and history
+/// while input to the program is paused.
+pub struct ViMode {
+    pub cursor: Point,
+    pub selection: Option<Selection>,
+    /// Direction of the last search, `?` searches towards older output and `/` towards newer
+    pub search_backwards: bool,
+    // `g` was pressed and is waiting for the second one
+    pending_g: bool,
+}
+
+impl ViMode {
+    pub fn new(cursor: Point) -> Self {
+        Self {
+            cursor,
+            selection: None,
+            search_backwards: true,
+            pending_g: false,
+        }
+    }
+
+    /// Moves the cursor, dragging the selection along
+    pub fn set_cursor(&mut self, point: Point) {
+        self.cursor = point;
+        if let Some(sel) = self.selection.as_mut() {
+            sel.update(point);
+        }
+    }
+
+    /// Handles keys that don't produce text input
+    pub fn handle_key(&mut self, keycode: Keycode, ctrl: bool, cells: &CharacterCellManager) -> ViAction {
+        match keycode {
+            Keycode::Escape => {
+                if self.selection.take().is_none() {
+                    return ViAction::Exit;
+                }
+                ViAction::None
+            }
+            Keycode::V if ctrl => {
+                self.toggle_selection(SelectionKind::Block);
+                ViAction::None
+            }
+            Keycode::Left => self.handle_char('h', cells),
+            Keycode::Down => self.handle_char('j', cells),
+            Keycode::Up => self.handle_char('k', cells),
+            Keycode::Right => self.handle_char('l', cells),
+            _ => ViAction::None,
+        }
+    }
+
+    pub fn handle_char(&mut self, ch: char, cells: &CharacterCellManager) -> ViAction {
+        let pending_g = std::mem::replace(&mut self.pending_g, false);
+        let mut cursor = self.cursor;
+        match ch {
+            'h' => cursor.col = cursor.col.saturating_sub(1),
+            'l' => cursor.col += 1,
+            'j' => cursor.line = (cursor.line + 1).min(cells.last_line()),
+            'k' => cursor.line = cursor.line.saturating_sub(1).max(cells.first_line()),
+            '0' => cursor.col = 0,
+            '$' => {
+                let row = cells.line(cursor.line);
+                cursor.col = row.and_then(|row| row.iter().rposition(|c| c.ch != ' ' && c.ch != '\0')).unwrap_or(0);
+            }
+            'w' => cursor = next_word_start(cells, cursor),
+            'b' => cursor = prev_word_start(cells, cursor),
+            'e' => cursor = word_end(cells, cursor),
+            'g' if pending_g => cursor = Point::new(cells.first_line(), 0),
+            'g' => {
+                self.pending_g = true;
+                return ViAction::None;
+            }
+            'G' => cursor = Point::new(cells.last_line(), 0),
+            'v' => self.toggle_selection(SelectionKind::Simple),
+            'V' => self.toggle_selection(SelectionKind::Line),
+            'y' => {
+                return match &self.selection {
+                    Some(sel) => ViAction::Yank(sel.text(cells)),
+                    None => ViAction::None,
+                };
+            }
+            '/' | '?' => {
+                self.search_backwards = ch == '?';
+                return ViAction::Search;
+            }
+            'n' => return ViAction::SearchNext { reverse: false },
+            'N' => return ViAction::SearchNext { reverse: true },
+            'q' => return ViAction::Exit,
+            _ => {}
+        }
+        // Rows in history can be narrower than the screen
+        cursor.col = cursor.col.min(row_len(cells, cursor.line).saturating_sub(1));
+        self.set_cursor(cursor);
+        ViAction::None
+    }
+
+    fn toggle_selection(&mut self, kind: SelectionKind) {
+        self.selection = match &self.selection {
+            Some(sel) if sel.kind == kind => None,
+            _ => Some(Selection::new(kind, self.cursor)),
+        };
+    }
+}
+
+fn row_len(cells: &CharacterCellManager, line: usize) -> usize {
+    cells.line(line).map_or(0, |row| row.len())
+}
+
+fn class_at(cells: &CharacterCellManager, point: Point) -> CharClass {
+    match cells.line(point.line).and_then(|row| row.get(point.col)).map(|c| c.ch) {
+        None | Some(' ') | Some('\0') | Some('\t') => CharClass::Blank,
+        Some(ch) if ch.is_alphanumeric() || ch == '_' => CharClass::Word,
+        Some(_) => CharClass::Punctuation,
+    }
+}
+
+/// The cell after `point` and whether a hard line break lies between them
+fn next(cells: &CharacterCellManager, point: Point) -> Option<(Point, bool)> {
+    if point.col + 1 < row_len(cells, point.line) {
+        Some((Point::new(point.line, point.col + 1), false))
+    } else if point.line < cells.last_line() {
+        Some((Point::new(point.line + 1, 0), !cells.is_wrapped(point.line)))
+    } else {
+        None
+    }
+}
+
+/// The cell before `point` and whether a hard line break lies between them
+fn prev(cells: &CharacterCellManager, point: Point) -> Option<(Point, bool)> {
+    if point.col > 0 {
+        Some((Point::new(point.line, point.col - 1), false))
+    } else if point.line > cells.first_line() {
+        let line = point.line - 1;
+        Some((Point::new(line, row_len(cells, line).saturating_sub(1)), !cells.is_wrapped(line)))
+    } else {
+        None
+    }
+}
+
+fn next_word_start(cells: &CharacterCellManager, mut point: Point) -> Point {
+    let class = class_at(cells, point);
+    // Leave the current word, a line break ends it too
+    while let Some((next_point, line_break)) = next(cells, point) {
+        point = next_point;
+        if line_break || class == CharClass::Blank || class_at(cells, point) != class {
+            break;
+        }
+    }
+    while class_at(cells, point) == CharClass::Blank {
+        match next(cells, point) {
+            Some((next_point, _)) => point = next_point,
+            None => break,
+        }
+    }
+    point
+}
+
+fn prev_word_start(cells: &CharacterCellManager, mut point: Point) -> Point {
+    match prev(cells, point) {
+        Some((prev_point, _)) => point = prev_point,
+        None => return point,
+    }
+    while class_at(cells, point) == CharClass::Blank {
+        match prev(cells, point) {
+            Some((prev_point, _)) => point = prev_point,
+            None => return point,
+        }
+    }
+    let class = class_at(cells, point);
+    while let Some((prev_point, line_break)) = prev(cells, point) {
+        if line_break || class_at(cells, prev_point) != class {
+            break;
+        }
+        point = prev_point;
+    }
+    point
+}
+
+fn word_end(cells: &CharacterCellManager, mut point: Point) -> Point {
+    match next(cells, point) {
+        Some((next_point, _)) => point = next_point,
+        None => return point,
+    }
+    while class_at(cells, point) == CharClass::Blank {
+        match next(cells, point) {
+            Some((next_point, _)) => point = next_point,
+            None => return point,
+        }
+    }
+    let class = class_at(cells, point);
+    while let Some((next_point, line_break)) = next(cells, point) {
+        if line_break || class_at(cells, next_point) != class {
+            break;
+        }
+        point = next_point;
+    }
+    point
+}
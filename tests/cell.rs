@@ -0,0 +1,36 @@
+//! Checks the packed representation of a cell.
+
+use rust_terminal::color::{CellColor, Color, BG_COL, BOLD_COL_ARR, FG_COL};
+use rust_terminal::hyperlink::{Hyperlink, Hyperlinks};
+use rust_terminal::term::{CharacterCell, Flags, CELL_SIZE_BUDGET};
+
+#[test]
+fn cell_fits_the_budget() {
+    assert!(std::mem::size_of::<CharacterCell>() <= CELL_SIZE_BUDGET);
+}
+
+#[test]
+fn flags_and_hyperlink_are_independent() {
+    let mut links = Hyperlinks::default();
+    let link = links.intern(Hyperlink { uri: "https://example.com".into(), id: None });
+    let mut cell = CharacterCell::new('x', CellColor::FG, CellColor::BG, Flags::BOLD);
+    cell.set_hyperlink(link);
+    cell.set_wrap(true);
+    assert!(cell.bold() && cell.wrap() && !cell.inverse());
+    assert_eq!(cell.hyperlink(), link);
+
+    cell.set_flags(Flags::INVERSE);
+    assert_eq!(cell.hyperlink(), link);
+    cell.set_hyperlink(None);
+    assert_eq!(cell.flags(), Flags::INVERSE);
+    assert_eq!(cell.hyperlink(), None);
+}
+
+#[test]
+fn colors_resolve_through_the_palette() {
+    assert_eq!(CellColor::FG.rgb(), FG_COL);
+    assert_eq!(CellColor::BG.rgb(), BG_COL);
+    assert_eq!(CellColor::Indexed(9).rgb(), BOLD_COL_ARR[1]);
+    assert_eq!(CellColor::Indexed(232).rgb(), Color::RGB(8, 8, 8));
+    assert_eq!(CellColor::Rgb(1, 2, 3).rgb(), Color::RGB(1, 2, 3));
+}
@@ -0,0 +1,134 @@
+//! Drives `Terminal` without a display and turns its screen into text that can be compared
+//! against golden files.
+#![allow(dead_code)]
+
+use std::fmt::Write;
+use std::path::PathBuf;
+use rust_terminal::color::Color;
+use rust_terminal::rust_ansi::term::Term;
+use rust_terminal::term::{CharacterCell, CharacterCellManager, Terminal};
+use rust_terminal::tty::Utf8Decoder;
+
+pub fn terminal(width: usize, height: usize) -> Term<CharacterCellManager> {
+    Term::new(Box::new(Terminal::new(width, height)))
+}
+
+/// Feeds `input` to a new `width` by `height` terminal
+pub fn run(width: usize, height: usize, input: &[u8]) -> Term<CharacterCellManager> {
+    let mut term = terminal(width, height);
+    term.write(Utf8Decoder::default().decode(input));
+    term
+}
+
+fn hex(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+fn style(cell: &CharacterCell) -> String {
+    let mut res = format!("fg={} bg={}", hex(cell.fg_col.rgb()), hex(cell.bg_col.rgb()));
+    if cell.bold() {
+        res.push_str(" bold");
+    }
+    if cell.inverse() {
+        res.push_str(" inverse");
+    }
+    if cell.hyperlink().is_some() {
+        res.push_str(" link");
+    }
+    res
+}
+
+/// Renders the history and screen as text, with a second grid naming the style of every cell.
+/// Rows are framed with `|`, a `+` at the end marks a row that wraps onto the next one.
+pub fn snapshot(cells: &CharacterCellManager) -> String {
+    let cursor = cells.cursor();
+    let mut res = String::new();
+    let _ = writeln!(res, "size {}x{}", cells.grid.width(), cells.grid.height());
+    let _ = writeln!(res, "cursor {},{}{}", cursor.x, cursor.y, if cursor.visible { "" } else { " hidden" });
+
+    let mut styles: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut style_grid = String::new();
+    for line in cells.first_line()..=cells.last_line() {
+        if line == cells.lines_scrolled {
+            text.push_str("screen:\n");
+            style_grid.push_str("screen:\n");
+        } else if line == cells.first_line() {
+            text.push_str("history:\n");
+            style_grid.push_str("history:\n");
+        }
+        let row = cells.line(line).unwrap();
+        text.push('|');
+        style_grid.push('|');
+        for cell in row {
+            text.push(match cell.ch {
+                '\0' => ' ',
+                // Keeps the grid aligned if a control character ever ends up in a cell
+                ch if ch.is_control() => '\u{fffd}',
+                ch => ch,
+            });
+            let style = style(cell);
+            let index = match styles.iter().position(|s| *s == style) {
+                Some(i) => i,
+                None => {
+                    styles.push(style);
+                    styles.len() - 1
+                }
+            };
+            style_grid.push(char::from_digit(index as u32, 36).unwrap_or('?'));
+        }
+        let end = if cells.is_wrapped(line) { "+\n" } else { "|\n" };
+        text.push_str(end);
+        style_grid.push_str(end);
+    }
+
+    res.push_str("\ntext:\n");
+    res.push_str(&text);
+    res.push_str("\nstyles:\n");
+    res.push_str(&style_grid);
+    res.push_str("\nlegend:\n");
+    for (i, style) in styles.iter().enumerate() {
+        let _ = writeln!(res, "{} {}", char::from_digit(i as u32, 36).unwrap_or('?'), style);
+    }
+    res
+}
+
+fn snapshot_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("snapshots").join(format!("{name}.snap"))
+}
+
+/// Compares `actual` with `tests/snapshots/<name>.snap`. Run with `BLESS=1` to write the file
+/// instead, after checking the new output is right.
+pub fn assert_snapshot(name: &str, actual: &str) {
+    let path = snapshot_path(name);
+    if std::env::var_os("BLESS").is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("{} is missing, run with BLESS=1 to create it", path.display()));
+    if expected != actual {
+        panic!("snapshot {name} doesn't match {}\n--- expected\n{expected}\n--- actual\n{actual}", path.display());
+    }
+}
+
+/// Feeds `input` to a new terminal and compares the result with the golden file `name`
+pub fn check(name: &str, width: usize, height: usize, input: &[u8]) {
+    let term = run(width, height, input);
+    assert_snapshot(name, &snapshot(term.framebuffer()));
+}
+
+/// For a case the terminal gets wrong so far, the same ones `tests/conformance_failures.txt`
+/// lists. The output is checked against `<name>.snap` like `check` does, so it can't change
+/// unnoticed, and `<name>.expected` holds the correct output, written by hand and never blessed.
+/// Fails once the output matches it, so the case can move over to `check`.
+pub fn check_known_failure(name: &str, width: usize, height: usize, input: &[u8]) {
+    let term = run(width, height, input);
+    let actual = snapshot(term.framebuffer());
+    let path = snapshot_path(name).with_extension("expected");
+    let expected = std::fs::read_to_string(&path).unwrap_or_else(|_| panic!("{} is missing", path.display()));
+    if actual == expected {
+        panic!("snapshot {} matches {} now, check it with `check` instead of `check_known_failure`", name, path.display());
+    }
+    assert_snapshot(name, &actual);
+}
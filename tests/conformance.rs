@@ -0,0 +1,687 @@
+//! Conformance cases in the spirit of vttest and esctest. Each case feeds a sequence to a fresh
+//! `Terminal` and checks the cursor, the screen, cell attributes or the replies afterwards.
+//!
+//! Not everything passes yet. The cases known to fail are listed in
+//! `tests/conformance_failures.txt`, the test fails if any other case fails or if a listed
+//! case starts passing. Run with `BLESS=1` to rewrite the list, and with `--nocapture` to see
+//! how much of each VT level passes.
+mod common;
+
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use rust_terminal::color::{Color, BG_COL, BOLD_COL_ARR, COL_ARR, FG_COL};
+use rust_terminal::term::{CharacterCellManager, CursorShape, MouseEncoding, MouseTracking, TerminalEvent};
+
+const WIDTH: usize = 10;
+const HEIGHT: usize = 6;
+/// Columns of each row `fill` writes, short of the edge so filling doesn't depend on wrapping
+const FILL_WIDTH: usize = 8;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Level {
+    Vt100,
+    Vt220,
+    Xterm,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Level::Vt100 => "VT100",
+            Level::Vt220 => "VT220",
+            Level::Xterm => "xterm",
+        })
+    }
+}
+
+enum Check {
+    /// 1-based column and row, like the escape sequences use
+    Cursor(usize, usize),
+    /// The text of a 1-based row, trailing spaces ignored
+    Row(usize, String),
+    Fg(usize, usize, Color),
+    Bg(usize, usize, Color),
+    Bold(usize, usize, bool),
+    Inverse(usize, usize, bool),
+    CursorVisible(bool),
+    /// Everything sent back to the program, concatenated
+    Reply(String),
+    ReplyPrefix(String),
+    /// The most recent title and icon name
+    Title(String, String),
+    History(usize),
+    Screen(&'static str, fn(&CharacterCellManager) -> bool),
+}
+
+struct Case {
+    name: String,
+    level: Level,
+    input: Vec<u8>,
+    checks: Vec<Check>,
+}
+
+impl Case {
+    fn new(level: Level, name: impl Into<String>, input: impl Into<Vec<u8>>) -> Self {
+        Self { name: name.into(), level, input: input.into(), checks: Vec::new() }
+    }
+
+    fn cursor(mut self, col: usize, row: usize) -> Self {
+        self.checks.push(Check::Cursor(col, row));
+        self
+    }
+
+    fn row(mut self, row: usize, text: &str) -> Self {
+        self.checks.push(Check::Row(row, text.to_string()));
+        self
+    }
+
+    /// Every row, top to bottom
+    fn screen(mut self, rows: &[String]) -> Self {
+        for (i, text) in rows.iter().enumerate() {
+            self.checks.push(Check::Row(i + 1, text.clone()));
+        }
+        self
+    }
+
+    fn fg(mut self, col: usize, row: usize, color: Color) -> Self {
+        self.checks.push(Check::Fg(col, row, color));
+        self
+    }
+
+    fn bg(mut self, col: usize, row: usize, color: Color) -> Self {
+        self.checks.push(Check::Bg(col, row, color));
+        self
+    }
+
+    fn bold(mut self, col: usize, row: usize, bold: bool) -> Self {
+        self.checks.push(Check::Bold(col, row, bold));
+        self
+    }
+
+    fn inverse(mut self, col: usize, row: usize, inverse: bool) -> Self {
+        self.checks.push(Check::Inverse(col, row, inverse));
+        self
+    }
+
+    fn cursor_visible(mut self, visible: bool) -> Self {
+        self.checks.push(Check::CursorVisible(visible));
+        self
+    }
+
+    fn reply(mut self, reply: &str) -> Self {
+        self.checks.push(Check::Reply(reply.to_string()));
+        self
+    }
+
+    fn reply_prefix(mut self, prefix: &str) -> Self {
+        self.checks.push(Check::ReplyPrefix(prefix.to_string()));
+        self
+    }
+
+    fn title(mut self, title: &str, icon_name: &str) -> Self {
+        self.checks.push(Check::Title(title.to_string(), icon_name.to_string()));
+        self
+    }
+
+    fn history(mut self, lines: usize) -> Self {
+        self.checks.push(Check::History(lines));
+        self
+    }
+
+    fn check(mut self, what: &'static str, f: fn(&CharacterCellManager) -> bool) -> Self {
+        self.checks.push(Check::Screen(what, f));
+        self
+    }
+
+    /// Feeds the input to a new terminal, returning what didn't match. Panics count as failures.
+    fn run(&self) -> Result<(), String> {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let term = common::run(WIDTH, HEIGHT, &self.input);
+            let cells = term.framebuffer();
+            let events = cells.take_events();
+            self.checks.iter()
+                .filter_map(|check| verify(check, cells, &events).err())
+                .collect::<Vec<_>>()
+        }));
+        match result {
+            Ok(errors) if errors.is_empty() => Ok(()),
+            Ok(errors) => Err(errors.join("; ")),
+            Err(e) => Err(format!(
+                "panicked: {}",
+                e.downcast_ref::<String>().map(String::as_str).or_else(|| e.downcast_ref::<&str>().copied()).unwrap_or("?"),
+            )),
+        }
+    }
+}
+
+fn verify(check: &Check, cells: &CharacterCellManager, events: &[TerminalEvent]) -> Result<(), String> {
+    let cell = |col: usize, row: usize| {
+        cells.grid.get(row - 1)
+            .and_then(|r| r.get(col - 1))
+            .ok_or_else(|| format!("no cell at {col},{row}"))
+    };
+    let expect = |what: String, expected: String, actual: String| {
+        if expected == actual {
+            Ok(())
+        } else {
+            Err(format!("{what}: expected {expected:?}, got {actual:?}"))
+        }
+    };
+    let replies = || events.iter()
+        .filter_map(|event| match event {
+            TerminalEvent::Reply(reply) => Some(reply.as_str()),
+            _ => None,
+        })
+        .collect::<String>();
+
+    match check {
+        Check::Cursor(col, row) => {
+            let cursor = cells.cursor();
+            expect("cursor".into(), format!("{col},{row}"), format!("{},{}", cursor.x + 1, cursor.y + 1))
+        }
+        Check::Row(row, text) => {
+            let actual = cells.grid.get(row - 1)
+                .map(|r| r.iter().map(|cell| if cell.ch == '\0' { ' ' } else { cell.ch }).collect::<String>())
+                .unwrap_or_default();
+            expect(format!("row {row}"), text.trim_end().to_string(), actual.trim_end().to_string())
+        }
+        Check::Fg(col, row, color) => expect(format!("fg at {col},{row}"), format!("{color:?}"), format!("{:?}", cell(*col, *row)?.fg_col.rgb())),
+        Check::Bg(col, row, color) => expect(format!("bg at {col},{row}"), format!("{color:?}"), format!("{:?}", cell(*col, *row)?.bg_col.rgb())),
+        Check::Bold(col, row, bold) => expect(format!("bold at {col},{row}"), bold.to_string(), cell(*col, *row)?.bold().to_string()),
+        Check::Inverse(col, row, inverse) => expect(format!("inverse at {col},{row}"), inverse.to_string(), cell(*col, *row)?.inverse().to_string()),
+        Check::CursorVisible(visible) => expect("cursor visible".into(), visible.to_string(), cells.cursor().visible.to_string()),
+        Check::Reply(reply) => expect("reply".into(), reply.clone(), replies()),
+        Check::ReplyPrefix(prefix) => {
+            let actual = replies();
+            if actual.starts_with(prefix.as_str()) {
+                Ok(())
+            } else {
+                Err(format!("reply: expected {prefix:?}..., got {actual:?}"))
+            }
+        }
+        Check::Title(title, icon_name) => {
+            let last = events.iter().rev().find_map(|event| match event {
+                TerminalEvent::Title { title, icon_name } => Some(format!("{title}/{icon_name}")),
+                _ => None,
+            });
+            expect("title/icon".into(), format!("{title}/{icon_name}"), last.unwrap_or_default())
+        }
+        Check::History(lines) => expect("history lines".into(), lines.to_string(), cells.grid.history_len().to_string()),
+        Check::Screen(what, f) => if f(cells) { Ok(()) } else { Err(format!("{what} doesn't hold")) },
+    }
+}
+
+fn cup(col: usize, row: usize) -> String {
+    format!("\x1b[{row};{col}H")
+}
+
+/// The text `fill` writes to a 1-based row
+fn fill_row(row: usize) -> String {
+    (0..FILL_WIDTH).map(|col| (b'a' + ((row - 1) * FILL_WIDTH + col) as u8 % 26) as char).collect()
+}
+
+/// Writes `fill_row` to every row of the default screen with absolute positioning
+fn fill() -> String {
+    (1..=HEIGHT).map(|row| cup(1, row) + &fill_row(row)).collect()
+}
+
+/// The filled screen with `f` applied to each 1-based row and its text
+fn filled_screen(f: impl Fn(usize, String) -> String) -> Vec<String> {
+    (1..=HEIGHT).map(|row| f(row, fill_row(row))).collect()
+}
+
+/// `text` with the 1-based columns `from..=to` blanked
+fn blank(text: &str, from: usize, to: usize) -> String {
+    text.chars().enumerate()
+        .map(|(i, ch)| if (from..=to).contains(&(i + 1)) { ' ' } else { ch })
+        .collect()
+}
+
+/// xterm's 256 color palette beyond the first 16
+fn xterm_color(n: usize) -> Color {
+    if n < 232 {
+        let level = |i: usize| if i == 0 { 0 } else { 55 + i as u8 * 40 };
+        let n = n - 16;
+        Color::RGB(level(n / 36), level(n / 6 % 6), level(n % 6))
+    } else {
+        let level = 8 + (n - 232) as u8 * 10;
+        Color::RGB(level, level, level)
+    }
+}
+
+fn cursor_motion(cases: &mut Vec<Case>) {
+    use Level::*;
+
+    for row in 1..=HEIGHT {
+        for col in 1..=WIDTH {
+            cases.push(Case::new(Vt100, format!("CUP {row};{col}"), cup(col, row)).cursor(col, row));
+        }
+    }
+    for row in 1..=HEIGHT {
+        cases.push(Case::new(Vt100, format!("HVP {row};{row}"), format!("\x1b[{row};{row}f")).cursor(row, row));
+    }
+    cases.push(Case::new(Vt100, "CUP without parameters homes", "\x1b[3;3H\x1b[H").cursor(1, 1));
+    cases.push(Case::new(Vt100, "CUP with only a row", "\x1b[3;3H\x1b[4H").cursor(1, 4));
+    cases.push(Case::new(Vt100, "CUP with only a column", "\x1b[3;3H\x1b[;5H").cursor(5, 1));
+    cases.push(Case::new(Vt100, "CUP 0;0 is 1;1", "\x1b[3;3H\x1b[0;0H").cursor(1, 1));
+    cases.push(Case::new(Vt100, "CUP past the end is clamped", "\x1b[99;99H").cursor(WIDTH, HEIGHT));
+    cases.push(Case::new(Vt100, "CUP past the end then print", "\x1b[99;99HX").row(HEIGHT, "         X"));
+    cases.push(Case::new(Vt100, "CUP past the right edge then print", "\x1b[2;99HX").row(2, "         X"));
+
+    // Relative motion from the middle of the screen, stopping at the edges
+    let (col, row): (usize, usize) = (5, 3);
+    for n in [0, 1, 2, 3, 5, 20] {
+        let d = n.max(1);
+        let moves = [
+            ('A', "CUU", col, row.saturating_sub(d).max(1)),
+            ('B', "CUD", col, (row + d).min(HEIGHT)),
+            ('C', "CUF", (col + d).min(WIDTH), row),
+            ('D', "CUB", col.saturating_sub(d).max(1), row),
+        ];
+        for (final_byte, name, x, y) in moves {
+            let input = format!("{}\x1b[{n}{final_byte}", cup(col, row));
+            cases.push(Case::new(Vt100, format!("{name} {n} from {col};{row}"), input).cursor(x, y));
+        }
+    }
+    for (final_byte, name, from, to) in [('A', "CUU", (4, 1), (4, 1)), ('B', "CUD", (4, HEIGHT), (4, HEIGHT)), ('C', "CUF", (WIDTH, 2), (WIDTH, 2)), ('D', "CUB", (1, 2), (1, 2))] {
+        let input = format!("{}\x1b[{final_byte}", cup(from.0, from.1));
+        cases.push(Case::new(Vt100, format!("{name} at the edge stays put"), input).cursor(to.0, to.1));
+    }
+    cases.push(Case::new(Vt100, "CUF doesn't wrap", format!("{}\x1b[5C", cup(8, 2))).cursor(WIDTH, 2));
+    cases.push(Case::new(Vt100, "CUB doesn't reverse wrap", format!("{}\x1b[5D", cup(3, 2))).cursor(1, 2));
+    cases.push(Case::new(Vt100, "CUF then print", format!("{}\x1b[3CX", cup(1, 1))).row(1, "   X"));
+    cases.push(Case::new(Vt100, "CUB then print", format!("{}abcd\x1b[2DX", cup(1, 1))).row(1, "abXd"));
+
+    for col in [1, 4, 10] {
+        cases.push(Case::new(Xterm, format!("CHA {col}"), format!("{}\x1b[{col}G", cup(5, 3))).cursor(col, 3));
+        cases.push(Case::new(Xterm, format!("HPA {col}"), format!("{}\x1b[{col}`", cup(5, 3))).cursor(col, 3));
+    }
+    cases.push(Case::new(Xterm, "CHA past the edge is clamped", "\x1b[99G").cursor(WIDTH, 1));
+    for row in [1, 4, HEIGHT] {
+        cases.push(Case::new(Xterm, format!("VPA {row}"), format!("{}\x1b[{row}d", cup(5, 3))).cursor(5, row));
+    }
+    cases.push(Case::new(Xterm, "VPA past the edge is clamped", "\x1b[99d").cursor(1, HEIGHT));
+    for n in [1, 2, 10] {
+        cases.push(Case::new(Xterm, format!("CNL {n}"), format!("{}\x1b[{n}E", cup(5, 3))).cursor(1, (3 + n).min(HEIGHT)));
+        cases.push(Case::new(Xterm, format!("CPL {n}"), format!("{}\x1b[{n}F", cup(5, 3))).cursor(1, 3usize.saturating_sub(n).max(1)));
+        cases.push(Case::new(Xterm, format!("HPR {n}"), format!("{}\x1b[{n}a", cup(5, 3))).cursor((5 + n).min(WIDTH), 3));
+        cases.push(Case::new(Xterm, format!("VPR {n}"), format!("{}\x1b[{n}e", cup(5, 3))).cursor(5, (3 + n).min(HEIGHT)));
+    }
+    cases.push(Case::new(Xterm, "CBT", format!("{}\x1b[Z", cup(10, 1))).cursor(9, 1));
+    cases.push(Case::new(Xterm, "CBT at the first stop", format!("{}\x1b[Z", cup(5, 1))).cursor(1, 1));
+    cases.push(Case::new(Xterm, "CHT", "\x1b[I").cursor(9, 1));
+    cases.push(Case::new(Xterm, "CHT 2 stops at the edge", "\x1b[2I").cursor(WIDTH, 1));
+
+    cases.push(Case::new(Vt100, "DECSC and DECRC", format!("{}\x1b7\x1b[H\x1b8", cup(4, 5))).cursor(4, 5));
+    cases.push(Case::new(Vt100, "DECRC restores attributes", "\x1b[1m\x1b7\x1b[0m\x1b8X").bold(1, 1, true));
+    cases.push(Case::new(Vt100, "DECRC without DECSC homes", format!("{}\x1b8", cup(4, 5))).cursor(1, 1));
+    cases.push(Case::new(Xterm, "SCOSC and SCORC", format!("{}\x1b[s\x1b[H\x1b[u", cup(4, 5))).cursor(4, 5));
+}
+
+fn control_characters(cases: &mut Vec<Case>) {
+    use Level::*;
+
+    cases.push(Case::new(Vt100, "CR", format!("{}\r", cup(5, 3))).cursor(1, 3));
+    cases.push(Case::new(Vt100, "CR then print", "abcdef\rXY").row(1, "XYcdef"));
+    cases.push(Case::new(Vt100, "LF keeps the column", format!("{}\n", cup(5, 3))).cursor(5, 4));
+    cases.push(Case::new(Vt100, "VT is LF", format!("{}\x0b", cup(5, 3))).cursor(5, 4));
+    cases.push(Case::new(Vt100, "FF is LF", format!("{}\x0c", cup(5, 3))).cursor(5, 4));
+    cases.push(Case::new(Vt100, "CRLF", format!("{}\r\n", cup(5, 3))).cursor(1, 4));
+    cases.push(Case::new(Vt100, "LF at the bottom scrolls", format!("top{}\n", cup(1, HEIGHT))).row(1, "").history(1).cursor(1, HEIGHT));
+    cases.push(Case::new(Vt100, "lines scroll into history", "1\r\n2\r\n3\r\n4\r\n5\r\n6\r\n7\r\n8").row(1, "3").row(HEIGHT, "8").history(2));
+    cases.push(Case::new(Vt100, "BS", format!("{}\x08", cup(5, 3))).cursor(4, 3));
+    cases.push(Case::new(Vt100, "BS doesn't erase", "abc\x08\x08").row(1, "abc").cursor(2, 1));
+    cases.push(Case::new(Vt100, "BS then print", "abc\x08\x08X").row(1, "aXc"));
+    cases.push(Case::new(Vt100, "BS at the left edge", format!("{}\x08", cup(1, 3))).cursor(1, 3));
+    cases.push(Case::new(Vt100, "BS at the left edge then print", "\x08X").row(1, "X"));
+    cases.push(Case::new(Vt100, "BEL is ignored", "a\x07b").row(1, "ab").cursor(3, 1));
+    cases.push(Case::new(Vt100, "NUL is ignored", "a\0b").row(1, "ab").cursor(3, 1));
+    cases.push(Case::new(Vt100, "SO and SI don't print", "a\x0e\x0fb").row(1, "ab"));
+
+    cases.push(Case::new(Vt100, "HT", "\t").cursor(9, 1));
+    cases.push(Case::new(Vt100, "HT doesn't erase", "abcdefgh\r\t").row(1, "abcdefgh").cursor(9, 1));
+    cases.push(Case::new(Vt100, "HT then print", "a\tX").row(1, "a       X"));
+    cases.push(Case::new(Vt100, "HT stops at the edge", "\t\t\t").cursor(WIDTH, 1));
+    cases.push(Case::new(Vt100, "HT from a stop", format!("{}\t", cup(9, 1))).cursor(WIDTH, 1));
+    cases.push(Case::new(Vt100, "HTS", format!("{}\x1bH\r\t", cup(4, 1))).cursor(4, 1));
+    cases.push(Case::new(Vt100, "TBC 0", format!("{}\x1b[g\r\t", cup(9, 1))).cursor(WIDTH, 1));
+    cases.push(Case::new(Vt100, "TBC 3", "\x1b[3g\t").cursor(WIDTH, 1));
+    cases.push(Case::new(Vt100, "HTS after TBC 3", format!("\x1b[3g{}\x1bH\r\t", cup(6, 1))).cursor(6, 1));
+
+    cases.push(Case::new(Vt100, "IND", format!("{}\x1bD", cup(5, 3))).cursor(5, 4));
+    cases.push(Case::new(Vt100, "IND at the bottom scrolls", format!("top{}\x1bD", cup(5, HEIGHT))).row(1, "").cursor(5, HEIGHT));
+    cases.push(Case::new(Vt100, "NEL", format!("{}\x1bE", cup(5, 3))).cursor(1, 4));
+    cases.push(Case::new(Vt100, "NEL at the bottom scrolls", format!("top{}\x1bE", cup(5, HEIGHT))).row(1, "").cursor(1, HEIGHT));
+    cases.push(Case::new(Vt100, "RI", format!("{}\x1bM", cup(5, 3))).cursor(5, 2));
+    cases.push(Case::new(Vt100, "RI at the top scrolls down", "top\x1b[H\x1bM").row(1, "").row(2, "top").cursor(1, 1));
+
+    cases.push(Case::new(Vt100, "DECALN", "\x1b#8").screen(&vec!["E".repeat(WIDTH); HEIGHT]).cursor(1, 1));
+    cases.push(Case::new(Vt100, "RIS", format!("{}\x1b[1mab\x1bc", cup(3, 3))).row(3, "").cursor(1, 1));
+    cases.push(Case::new(Vt100, "RIS resets attributes", "\x1b[1;7m\x1bcX").bold(1, 1, false).inverse(1, 1, false));
+}
+
+fn wrapping(cases: &mut Vec<Case>) {
+    use Level::*;
+
+    let full = "abcdefghij";
+    cases.push(Case::new(Vt100, "printing fills the last column", full).row(1, full).row(2, ""));
+    cases.push(Case::new(Vt100, "the cursor waits at the last column", full).cursor(WIDTH, 1));
+    cases.push(Case::new(Vt100, "the next character wraps", format!("{full}k")).row(1, full).row(2, "k").cursor(2, 2));
+    cases.push(Case::new(Vt100, "CR cancels the pending wrap", format!("{full}\rX")).row(1, "Xbcdefghij").row(2, ""));
+    cases.push(Case::new(Vt100, "CUB cancels the pending wrap", format!("{full}\x1b[DX")).row(1, "abcdefghXj").row(2, ""));
+    cases.push(Case::new(Vt100, "BS cancels the pending wrap", format!("{full}\x08X")).row(1, "abcdefghXj"));
+    cases.push(Case::new(Vt100, "LF after a full row doesn't add a line", format!("{full}\r\nX")).row(2, "X").row(3, ""));
+    cases.push(Case::new(Vt100, "a wrap at the bottom scrolls", format!("{}{full}k", cup(1, HEIGHT))).row(HEIGHT - 1, full).row(HEIGHT, "k").history(1));
+    cases.push(Case::new(Vt100, "text longer than two rows", "abcdefghijklmnopqrstuvwxy").row(1, full).row(2, "klmnopqrst").row(3, "uvwxy").cursor(6, 3));
+    cases.push(Case::new(Vt100, "printing at the last column", format!("{}X", cup(WIDTH, 2))).row(2, "         X").cursor(WIDTH, 2));
+    cases.push(Case::new(Xterm, "wrapped rows are marked", format!("{full}k")).check("row 1 continues on row 2", |cells| cells.is_wrapped(cells.lines_scrolled)));
+    cases.push(Case::new(Xterm, "full rows without a wrap aren't marked", format!("{full}\r\nk")).check("row 1 isn't wrapped", |cells| !cells.is_wrapped(cells.lines_scrolled)));
+    cases.push(Case::new(Vt100, "DECAWM off", format!("\x1b[?7l{full}XY")).row(1, "abcdefghiY").row(2, ""));
+    cases.push(Case::new(Vt100, "DECAWM back on", format!("\x1b[?7l\x1b[?7h{full}k")).row(2, "k"));
+}
+
+fn erasing(cases: &mut Vec<Case>) {
+    use Level::*;
+
+    for row in 1..=HEIGHT {
+        for col in [1, 4, FILL_WIDTH] {
+            let at = cup(col, row);
+            let below = filled_screen(|r, text| match r.cmp(&row) {
+                std::cmp::Ordering::Less => text,
+                std::cmp::Ordering::Equal => blank(&text, col, WIDTH),
+                std::cmp::Ordering::Greater => String::new(),
+            });
+            cases.push(Case::new(Vt100, format!("ED 0 at {row};{col}"), format!("{}{at}\x1b[J", fill())).screen(&below).cursor(col, row));
+            let above = filled_screen(|r, text| match r.cmp(&row) {
+                std::cmp::Ordering::Less => String::new(),
+                std::cmp::Ordering::Equal => blank(&text, 1, col),
+                std::cmp::Ordering::Greater => text,
+            });
+            cases.push(Case::new(Vt100, format!("ED 1 at {row};{col}"), format!("{}{at}\x1b[1J", fill())).screen(&above).cursor(col, row));
+            cases.push(Case::new(Vt100, format!("ED 2 at {row};{col}"), format!("{}{at}\x1b[2J", fill())).screen(&vec![String::new(); HEIGHT]).cursor(col, row));
+        }
+    }
+    cases.push(Case::new(Vt100, "ED without a parameter", format!("{}{}\x1b[J", fill(), cup(1, 2))).row(1, &fill_row(1)).row(2, ""));
+    cases.push(Case::new(Xterm, "ED 3 clears history", "1\r\n2\r\n3\r\n4\r\n5\r\n6\r\n7\r\n8\x1b[3J").history(0).row(HEIGHT, "8"));
+    cases.push(Case::new(Xterm, "ED 2 keeps history", "1\r\n2\r\n3\r\n4\r\n5\r\n6\r\n7\r\n8\x1b[2J").history(2));
+    cases.push(Case::new(Vt100, "ED with an unknown parameter is ignored", format!("{}\x1b[9J", fill())).screen(&filled_screen(|_, text| text)));
+    cases.push(Case::new(Vt100, "ED uses the background color", "\x1b[44m\x1b[2J").bg(1, 1, COL_ARR[4]).bg(WIDTH, HEIGHT, COL_ARR[4]));
+
+    let row = 3;
+    for col in 1..=FILL_WIDTH {
+        let at = cup(col, row);
+        let text = fill_row(row);
+        cases.push(Case::new(Vt100, format!("EL 0 at column {col}"), format!("{}{at}\x1b[K", fill())).row(row, &blank(&text, col, WIDTH)).row(row - 1, &fill_row(row - 1)).cursor(col, row));
+        cases.push(Case::new(Vt100, format!("EL 1 at column {col}"), format!("{}{at}\x1b[1K", fill())).row(row, &blank(&text, 1, col)).row(row + 1, &fill_row(row + 1)).cursor(col, row));
+        cases.push(Case::new(Vt100, format!("EL 2 at column {col}"), format!("{}{at}\x1b[2K", fill())).row(row, "").row(row + 1, &fill_row(row + 1)).cursor(col, row));
+    }
+    cases.push(Case::new(Vt100, "EL 0 at the last column", format!("{}abcdefghij\x1b[K", cup(1, 2))).row(2, "abcdefghi"));
+    cases.push(Case::new(Vt100, "EL 1 at the last column", format!("{}abcdefghij\x1b[1K", cup(1, 2))).row(2, ""));
+    cases.push(Case::new(Vt100, "EL uses the background color", "\x1b[41m\x1b[K").bg(1, 1, COL_ARR[1]).bg(WIDTH, 1, COL_ARR[1]));
+    cases.push(Case::new(Vt100, "EL with an unknown parameter is ignored", format!("{}{}\x1b[9K", fill(), cup(1, 3))).row(3, &fill_row(3)));
+
+    for n in [1, 3, 20] {
+        let expected = blank(&fill_row(3), 3, 2 + n);
+        cases.push(Case::new(Vt220, format!("ECH {n}"), format!("{}{}\x1b[{n}X", fill(), cup(3, 3))).row(3, &expected).cursor(3, 3));
+    }
+    cases.push(Case::new(Vt220, "ECH without a parameter", format!("{}{}\x1b[X", fill(), cup(1, 3))).row(3, &blank(&fill_row(3), 1, 1)));
+}
+
+fn editing(cases: &mut Vec<Case>) {
+    use Level::*;
+
+    for row in 1..=HEIGHT {
+        for n in [1, 2, HEIGHT] {
+            let screen = filled_screen(|r, _| {
+                if r < row {
+                    fill_row(r)
+                } else if r < row + n {
+                    String::new()
+                } else {
+                    fill_row(r - n)
+                }
+            });
+            cases.push(Case::new(Vt220, format!("IL {n} at row {row}"), format!("{}{}\x1b[{n}L", fill(), cup(1, row))).screen(&screen));
+            let screen = filled_screen(|r, _| if r < row { fill_row(r) } else if r + n <= HEIGHT { fill_row(r + n) } else { String::new() });
+            cases.push(Case::new(Vt220, format!("DL {n} at row {row}"), format!("{}{}\x1b[{n}M", fill(), cup(1, row))).screen(&screen));
+        }
+    }
+    cases.push(Case::new(Vt220, "IL moves to the first column", format!("{}\x1b[L", cup(5, 3))).cursor(1, 3));
+    cases.push(Case::new(Vt220, "DL moves to the first column", format!("{}\x1b[M", cup(5, 3))).cursor(1, 3));
+    cases.push(Case::new(Vt220, "IL with a huge count", format!("{}{}\x1b[9999L", fill(), cup(1, 2))).row(1, &fill_row(1)).row(2, "").row(HEIGHT, ""));
+    cases.push(Case::new(Vt220, "IL after CUP past the end", format!("{}\x1b[99;1H\x1b[L", fill())).row(HEIGHT, "").row(1, &fill_row(1)));
+    cases.push(Case::new(Vt220, "IL uses the background color", "\x1b[42m\x1b[L").bg(1, 1, COL_ARR[2]));
+
+    let text = fill_row(3);
+    for n in [1, 2, 5] {
+        let mut inserted: String = text[..2].to_string();
+        inserted.push_str(&" ".repeat(n));
+        inserted.push_str(&text[2..]);
+        inserted.truncate(WIDTH);
+        cases.push(Case::new(Vt220, format!("ICH {n}"), format!("{}{}\x1b[{n}@", fill(), cup(3, 3))).row(3, &inserted).cursor(3, 3));
+        let deleted = format!("{}{}", &text[..2], text.get(2 + n..).unwrap_or(""));
+        cases.push(Case::new(Vt220, format!("DCH {n}"), format!("{}{}\x1b[{n}P", fill(), cup(3, 3))).row(3, &deleted).cursor(3, 3));
+    }
+    cases.push(Case::new(Vt220, "ICH pushes text off the edge", format!("{}abcdefghij\x1b[H\x1b[3@", cup(1, 1))).row(1, "   abcdefg"));
+    cases.push(Case::new(Vt220, "DCH with a huge count", format!("{}{}\x1b[999P", fill(), cup(3, 3))).row(3, &text[..2]));
+    cases.push(Case::new(Xterm, "REP", "a\x1b[3b").row(1, "aaaa").cursor(5, 1));
+    cases.push(Case::new(Xterm, "IRM inserts", "abc\x1b[H\x1b[4hX").row(1, "Xabc"));
+    cases.push(Case::new(Xterm, "IRM off replaces", "abc\x1b[H\x1b[4h\x1b[4lX").row(1, "Xbc"));
+}
+
+fn scrolling(cases: &mut Vec<Case>) {
+    use Level::*;
+
+    for n in [1, 2, HEIGHT, 20] {
+        let up = filled_screen(|r, _| if r + n <= HEIGHT { fill_row(r + n) } else { String::new() });
+        cases.push(Case::new(Xterm, format!("SU {n}"), format!("{}\x1b[{n}S", fill())).screen(&up));
+        let down = filled_screen(|r, _| if r > n { fill_row(r - n) } else { String::new() });
+        cases.push(Case::new(Xterm, format!("SD {n}"), format!("{}\x1b[{n}T", fill())).screen(&down));
+    }
+    cases.push(Case::new(Xterm, "SU keeps the cursor", format!("{}\x1b[S", cup(4, 4))).cursor(4, 4));
+    cases.push(Case::new(Xterm, "SD keeps the cursor", format!("{}\x1b[T", cup(4, 4))).cursor(4, 4));
+    cases.push(Case::new(Xterm, "SU uses the background color", "\x1b[43m\x1b[S").bg(1, HEIGHT, COL_ARR[3]));
+
+    // Scrolling regions
+    let region = "\x1b[2;5r";
+    cases.push(Case::new(Vt100, "DECSTBM homes the cursor", format!("{}{region}", cup(4, 4))).cursor(1, 1));
+    let up_in_region = filled_screen(|r, text| match r {
+        2..=4 => fill_row(r + 1),
+        5 => String::new(),
+        _ => text,
+    });
+    cases.push(Case::new(Vt100, "LF at the region bottom", format!("{}{region}{}\n", fill(), cup(1, 5))).screen(&up_in_region).history(0));
+    cases.push(Case::new(Vt100, "IND at the region bottom", format!("{}{region}{}\x1bD", fill(), cup(1, 5))).screen(&up_in_region));
+    cases.push(Case::new(Xterm, "SU in a region", format!("{}{region}\x1b[S", fill())).screen(&up_in_region));
+    let down_in_region = filled_screen(|r, text| match r {
+        2 => String::new(),
+        3..=5 => fill_row(r - 1),
+        _ => text,
+    });
+    cases.push(Case::new(Vt100, "RI at the region top", format!("{}{region}{}\x1bM", fill(), cup(1, 2))).screen(&down_in_region));
+    cases.push(Case::new(Xterm, "SD in a region", format!("{}{region}\x1b[T", fill())).screen(&down_in_region));
+    cases.push(Case::new(Vt220, "IL in a region", format!("{}{region}{}\x1b[L", fill(), cup(1, 2))).screen(&down_in_region));
+    cases.push(Case::new(Vt220, "DL in a region", format!("{}{region}{}\x1b[M", fill(), cup(1, 2))).screen(&up_in_region));
+    cases.push(Case::new(Vt100, "LF below the region doesn't scroll", format!("{}{region}{}\n", fill(), cup(1, HEIGHT))).screen(&filled_screen(|_, text| text)).cursor(1, HEIGHT));
+    cases.push(Case::new(Vt100, "CUD stops at the region bottom", format!("{region}{}\x1b[9B", cup(1, 3))).cursor(1, 5));
+    cases.push(Case::new(Vt100, "CUU stops at the region top", format!("{region}{}\x1b[9A", cup(1, 4))).cursor(1, 2));
+    cases.push(Case::new(Vt100, "DECSTBM without parameters resets", format!("{}{region}\x1b[r{}\n", fill(), cup(1, HEIGHT))).row(1, &fill_row(2)));
+    cases.push(Case::new(Vt100, "DECSTBM with top below bottom is ignored", format!("{}\x1b[5;2r{}\n", fill(), cup(1, HEIGHT))).row(1, &fill_row(2)));
+    cases.push(Case::new(Vt100, "DECOM makes CUP relative", format!("{region}\x1b[?6h\x1b[1;1H")).cursor(1, 2));
+    cases.push(Case::new(Vt100, "DECOM clamps CUP to the region", format!("{region}\x1b[?6h\x1b[99;1H")).cursor(1, 5));
+}
+
+fn attributes(cases: &mut Vec<Case>) {
+    use Level::*;
+
+    cases.push(Case::new(Vt100, "text starts plain", "X").fg(1, 1, FG_COL).bg(1, 1, BG_COL).bold(1, 1, false).inverse(1, 1, false));
+    cases.push(Case::new(Vt100, "SGR 1", "\x1b[1mX").bold(1, 1, true));
+    cases.push(Case::new(Vt100, "SGR 7", "\x1b[7mX").inverse(1, 1, true));
+    cases.push(Case::new(Vt100, "SGR 0 resets bold", "\x1b[1m\x1b[0mX").bold(1, 1, false));
+    cases.push(Case::new(Vt100, "SGR 0 resets inverse", "\x1b[7m\x1b[0mX").inverse(1, 1, false));
+    cases.push(Case::new(Vt100, "SGR without parameters resets", "\x1b[1;7m\x1b[mX").bold(1, 1, false).inverse(1, 1, false));
+    cases.push(Case::new(Vt100, "SGR 1;7 together", "\x1b[1;7mX").bold(1, 1, true).inverse(1, 1, true));
+    cases.push(Case::new(Vt100, "SGR only affects later text", "a\x1b[1mb").bold(1, 1, false).bold(2, 1, true));
+    cases.push(Case::new(Vt100, "SGR survives cursor motion", "\x1b[1m\x1b[3;3HX").bold(3, 3, true));
+    cases.push(Case::new(Vt100, "SGR 4 doesn't disturb bold", "\x1b[1;4mX").bold(1, 1, true));
+    cases.push(Case::new(Vt100, "SGR 5 doesn't disturb bold", "\x1b[1;5mX").bold(1, 1, true));
+    cases.push(Case::new(Vt220, "SGR 22", "\x1b[1m\x1b[22mX").bold(1, 1, false));
+    cases.push(Case::new(Vt220, "SGR 27", "\x1b[7m\x1b[27mX").inverse(1, 1, false));
+    cases.push(Case::new(Vt220, "SGR 22 keeps inverse", "\x1b[1;7m\x1b[22mX").inverse(1, 1, true));
+    cases.push(Case::new(Vt220, "SGR 24 and 25 are accepted", "\x1b[1m\x1b[24;25mX").bold(1, 1, true));
+
+    for i in 0..8 {
+        cases.push(Case::new(Xterm, format!("SGR {}", 30 + i), format!("\x1b[{}mX", 30 + i)).fg(1, 1, COL_ARR[i]).bg(1, 1, BG_COL));
+        cases.push(Case::new(Xterm, format!("SGR {}", 40 + i), format!("\x1b[{}mX", 40 + i)).bg(1, 1, COL_ARR[i]).fg(1, 1, FG_COL));
+        cases.push(Case::new(Xterm, format!("SGR {}", 90 + i), format!("\x1b[{}mX", 90 + i)).fg(1, 1, BOLD_COL_ARR[i]));
+        cases.push(Case::new(Xterm, format!("SGR {}", 100 + i), format!("\x1b[{}mX", 100 + i)).bg(1, 1, BOLD_COL_ARR[i]).fg(1, 1, FG_COL));
+    }
+    cases.push(Case::new(Xterm, "SGR 39", "\x1b[31;42m\x1b[39mX").fg(1, 1, FG_COL).bg(1, 1, COL_ARR[2]));
+    cases.push(Case::new(Xterm, "SGR 49", "\x1b[31;42m\x1b[49mX").bg(1, 1, BG_COL).fg(1, 1, COL_ARR[1]));
+    cases.push(Case::new(Xterm, "SGR 0 resets colors", "\x1b[31;42m\x1b[0mX").fg(1, 1, FG_COL).bg(1, 1, BG_COL));
+    cases.push(Case::new(Xterm, "colors and bold together", "\x1b[1;34mX").bold(1, 1, true));
+
+    for n in [0, 7, 8, 15] {
+        let color = if n < 8 { COL_ARR[n] } else { BOLD_COL_ARR[n - 8] };
+        cases.push(Case::new(Xterm, format!("SGR 38;5;{n}"), format!("\x1b[38;5;{n}mX")).fg(1, 1, color));
+        cases.push(Case::new(Xterm, format!("SGR 48;5;{n}"), format!("\x1b[48;5;{n}mX")).bg(1, 1, color));
+    }
+    for n in [16, 21, 46, 102, 196, 231, 232, 244, 255] {
+        cases.push(Case::new(Xterm, format!("SGR 38;5;{n}"), format!("\x1b[38;5;{n}mX")).fg(1, 1, xterm_color(n)));
+        cases.push(Case::new(Xterm, format!("SGR 48;5;{n}"), format!("\x1b[48;5;{n}mX")).bg(1, 1, xterm_color(n)));
+    }
+    for (r, g, b) in [(0, 0, 0), (255, 255, 255), (1, 2, 3), (250, 128, 0)] {
+        cases.push(Case::new(Xterm, format!("SGR 38;2;{r};{g};{b}"), format!("\x1b[38;2;{r};{g};{b}mX")).fg(1, 1, Color::RGB(r, g, b)));
+        cases.push(Case::new(Xterm, format!("SGR 48;2;{r};{g};{b}"), format!("\x1b[48;2;{r};{g};{b}mX")).bg(1, 1, Color::RGB(r, g, b)));
+    }
+    cases.push(Case::new(Xterm, "SGR 38;5 followed by more", "\x1b[38;5;1;1mX").fg(1, 1, COL_ARR[1]).bold(1, 1, true));
+    cases.push(Case::new(Xterm, "SGR 38;2 followed by more", "\x1b[38;2;1;2;3;7mX").fg(1, 1, Color::RGB(1, 2, 3)).inverse(1, 1, true));
+    cases.push(Case::new(Xterm, "truncated SGR 38;5 is ignored", "\x1b[38;5mX").row(1, "X"));
+    cases.push(Case::new(Xterm, "truncated SGR 38;2 is ignored", "\x1b[38;2;1mX").row(1, "X"));
+    cases.push(Case::new(Xterm, "truncated SGR 48 is ignored", "\x1b[48mX").row(1, "X"));
+    cases.push(Case::new(Xterm, "SGR 38 with an unknown color space is ignored", "\x1b[38;9mX").row(1, "X"));
+    cases.push(Case::new(Xterm, "SGR 38;5 out of range is ignored", "\x1b[38;5;300mX").row(1, "X"));
+    cases.push(Case::new(Xterm, "unknown SGR is ignored", "\x1b[1;77mX").bold(1, 1, true).row(1, "X"));
+}
+
+fn modes_and_reports(cases: &mut Vec<Case>) {
+    use Level::*;
+
+    cases.push(Case::new(Vt100, "DSR 5", "\x1b[5n").reply("\x1b[0n"));
+    cases.push(Case::new(Vt100, "DSR 6 at home", "\x1b[6n").reply("\x1b[1;1R"));
+    for (col, row) in [(4, 2), (WIDTH, HEIGHT), (1, HEIGHT)] {
+        cases.push(Case::new(Vt100, format!("DSR 6 at {row};{col}"), format!("{}\x1b[6n", cup(col, row))).reply(&format!("\x1b[{row};{col}R")));
+    }
+    cases.push(Case::new(Vt100, "DA", "\x1b[c").reply_prefix("\x1b[?"));
+    cases.push(Case::new(Vt100, "DA 0", "\x1b[0c").reply_prefix("\x1b[?"));
+    cases.push(Case::new(Vt220, "DA2", "\x1b[>c").reply_prefix("\x1b[>"));
+    cases.push(Case::new(Vt220, "DA reports VT220", "\x1b[c").reply_prefix("\x1b[?62"));
+    cases.push(Case::new(Xterm, "DECRQM for DECAWM", "\x1b[?7$p").reply("\x1b[?7;1$y"));
+    cases.push(Case::new(Xterm, "DECRQM for synchronized output", "\x1b[?2026$p").reply("\x1b[?2026;2$y"));
+    cases.push(Case::new(Xterm, "DECRQM for a set mode", "\x1b[?2004h\x1b[?2004$p").reply("\x1b[?2004;1$y"));
+    cases.push(Case::new(Xterm, "DECRQM for an unknown mode", "\x1b[?31337$p").reply("\x1b[?31337;0$y"));
+
+    cases.push(Case::new(Vt220, "DECTCEM hides the cursor", "\x1b[?25l").cursor_visible(false));
+    cases.push(Case::new(Vt220, "DECTCEM shows the cursor", "\x1b[?25l\x1b[?25h").cursor_visible(true));
+    cases.push(Case::new(Vt220, "the cursor starts visible", "").cursor_visible(true));
+
+    cases.push(Case::new(Xterm, "bracketed paste on", "\x1b[?2004h").check("bracketed paste is on", |cells| cells.modes.bracketed_paste));
+    cases.push(Case::new(Xterm, "bracketed paste off", "\x1b[?2004h\x1b[?2004l").check("bracketed paste is off", |cells| !cells.modes.bracketed_paste));
+    cases.push(Case::new(Xterm, "synchronized output on", "\x1b[?2026h").check("synchronized output is on", |cells| cells.modes.synchronized_output));
+    cases.push(Case::new(Xterm, "synchronized output off", "\x1b[?2026h\x1b[?2026l").check("synchronized output is off", |cells| !cells.modes.synchronized_output));
+    cases.push(Case::new(Xterm, "X10 mouse", "\x1b[?9h").check("X10 tracking", |cells| cells.modes.mouse_tracking == MouseTracking::X10));
+    cases.push(Case::new(Xterm, "normal mouse", "\x1b[?1000h").check("normal tracking", |cells| cells.modes.mouse_tracking == MouseTracking::Normal));
+    cases.push(Case::new(Xterm, "button event mouse", "\x1b[?1002h").check("button event tracking", |cells| cells.modes.mouse_tracking == MouseTracking::ButtonEvent));
+    cases.push(Case::new(Xterm, "any event mouse", "\x1b[?1003h").check("any event tracking", |cells| cells.modes.mouse_tracking == MouseTracking::AnyEvent));
+    cases.push(Case::new(Xterm, "mouse off", "\x1b[?1000h\x1b[?1000l").check("no tracking", |cells| cells.modes.mouse_tracking == MouseTracking::Off));
+    cases.push(Case::new(Xterm, "SGR mouse encoding", "\x1b[?1006h").check("SGR encoding", |cells| cells.modes.mouse_encoding == MouseEncoding::Sgr));
+    cases.push(Case::new(Xterm, "urxvt mouse encoding", "\x1b[?1015h").check("urxvt encoding", |cells| cells.modes.mouse_encoding == MouseEncoding::Urxvt));
+    cases.push(Case::new(Xterm, "several modes at once", "\x1b[?1000;1006;2004h").check("all three modes are on", |cells| {
+        cells.modes.mouse_tracking == MouseTracking::Normal && cells.modes.mouse_encoding == MouseEncoding::Sgr && cells.modes.bracketed_paste
+    }));
+    cases.push(Case::new(Xterm, "DECSCUSR 4", "\x1b[4 q").check("steady underline", |cells| {
+        let cursor = cells.cursor();
+        cursor.shape == CursorShape::Underline && !cursor.blinking
+    }));
+    cases.push(Case::new(Xterm, "DECSCUSR 5", "\x1b[5 q").check("blinking bar", |cells| {
+        let cursor = cells.cursor();
+        cursor.shape == CursorShape::Bar && cursor.blinking
+    }));
+    cases.push(Case::new(Xterm, "DECSCUSR 0", "\x1b[6 q\x1b[0 q").check("blinking block", |cells| {
+        let cursor = cells.cursor();
+        cursor.shape == CursorShape::Block && cursor.blinking
+    }));
+
+    cases.push(Case::new(Xterm, "OSC 0", "\x1b]0;both\x07").title("both", "both"));
+    cases.push(Case::new(Xterm, "OSC 1", "\x1b]1;icon\x07").title("", "icon"));
+    cases.push(Case::new(Xterm, "OSC 2", "\x1b]2;title\x07").title("title", ""));
+    cases.push(Case::new(Xterm, "OSC 2 ended by ST", "\x1b]2;title\x1b\\").title("title", ""));
+    cases.push(Case::new(Xterm, "OSC 2 keeps the icon name", "\x1b]1;icon\x07\x1b]2;title\x07").title("title", "icon"));
+    cases.push(Case::new(Xterm, "XTWINOPS 22 and 23", "\x1b]2;old\x07\x1b[22t\x1b]2;new\x07\x1b[23t").title("old", ""));
+    cases.push(Case::new(Xterm, "XTWINOPS 23 for the title only", "\x1b]2;old\x07\x1b]1;old\x07\x1b[22t\x1b]2;new\x07\x1b]1;new\x07\x1b[23;2t").title("old", "new"));
+    cases.push(Case::new(Xterm, "OSC doesn't print", "\x1b]2;title\x07X").row(1, "X").cursor(2, 1));
+    cases.push(Case::new(Xterm, "unknown OSC doesn't print", "\x1b]777;notify;a;b\x07X").row(1, "X"));
+    cases.push(Case::new(Xterm, "unknown CSI doesn't print", "\x1b[?1049hX").row(1, "X"));
+}
+
+fn all_cases() -> Vec<Case> {
+    let mut cases = Vec::new();
+    cursor_motion(&mut cases);
+    control_characters(&mut cases);
+    wrapping(&mut cases);
+    erasing(&mut cases);
+    editing(&mut cases);
+    scrolling(&mut cases);
+    attributes(&mut cases);
+    modes_and_reports(&mut cases);
+    cases
+}
+
+#[test]
+fn conformance() {
+    let cases = all_cases();
+    let mut names = std::collections::HashSet::new();
+    for case in &cases {
+        assert!(names.insert(&case.name), "duplicate case name {:?}", case.name);
+    }
+
+    // The terminal panics on some input, keep that from flooding the output
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let results: Vec<_> = cases.iter().map(|case| (case, case.run())).collect();
+    panic::set_hook(hook);
+
+    println!("\n{:<8} {:>7} {:>7} {:>7}", "level", "passed", "cases", "percent");
+    for level in [Level::Vt100, Level::Vt220, Level::Xterm] {
+        let (passed, total) = results.iter()
+            .filter(|(case, _)| case.level == level)
+            .fold((0, 0), |(passed, total), (_, result)| (passed + result.is_ok() as usize, total + 1));
+        println!("{:<8} {:>7} {:>7} {:>6.1}%", level.to_string(), passed, total, 100.0 * passed as f64 / total as f64);
+    }
+    let passed = results.iter().filter(|(_, result)| result.is_ok()).count();
+    println!("{:<8} {:>7} {:>7} {:>6.1}%\n", "all", passed, results.len(), 100.0 * passed as f64 / results.len() as f64);
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("conformance_failures.txt");
+    let failing: Vec<String> = results.iter()
+        .filter(|(_, result)| result.is_err())
+        .map(|(case, _)| format!("[{}] {}", case.level, case.name))
+        .collect();
+    if std::env::var_os("BLESS").is_some() {
+        std::fs::write(&path, failing.join("\n") + "\n").unwrap();
+        return;
+    }
+    let known = std::fs::read_to_string(&path).unwrap_or_default();
+    let known: Vec<&str> = known.lines().filter(|line| !line.is_empty()).collect();
+
+    let mut problems = Vec::new();
+    for (case, result) in &results {
+        let key = format!("[{}] {}", case.level, case.name);
+        match result {
+            Err(e) if !known.contains(&key.as_str()) => problems.push(format!("{key} fails: {e}")),
+            Ok(()) if known.contains(&key.as_str()) => problems.push(format!("{key} passes now, remove it from {}", path.display())),
+            _ => {}
+        }
+    }
+    assert!(problems.is_empty(), "{}", problems.join("\n"));
+}
@@ -0,0 +1,100 @@
+//! Checks which parts of the screen `Terminal` reports as changed since the last frame.
+
+mod common;
+
+use std::ops::Range;
+use rust_terminal::rust_ansi::term::Term;
+use rust_terminal::term::CharacterCellManager;
+
+const WIDTH: usize = 10;
+const HEIGHT: usize = 4;
+
+/// A terminal with some text on every row that has already been drawn once
+fn drawn() -> Term<CharacterCellManager> {
+    let mut term = common::run(WIDTH, HEIGHT, b"one\r\ntwo\r\nthree\r\nfour");
+    term.completed_render();
+    term
+}
+
+fn rows(term: &Term<CharacterCellManager>) -> Vec<Option<Range<usize>>> {
+    term.framebuffer().damage.rows.clone()
+}
+
+#[test]
+fn new_terminal_is_all_damaged() {
+    let term = common::terminal(WIDTH, HEIGHT);
+    assert_eq!(rows(&term), vec![Some(0..WIDTH); HEIGHT]);
+}
+
+#[test]
+fn completed_render_clears_damage() {
+    let term = drawn();
+    assert_eq!(rows(&term), vec![None; HEIGHT]);
+    assert_eq!(term.framebuffer().damage.scrolled, 0);
+}
+
+#[test]
+fn printing_damages_only_the_changed_columns() {
+    let mut term = drawn();
+    term.write("\x1b[2;3Hab\x1b[4;7Hc".to_string());
+    assert_eq!(rows(&term), vec![None, Some(2..4), None, Some(6..7)]);
+}
+
+#[test]
+fn scrolling_moves_damage_and_damages_new_rows() {
+    let mut term = drawn();
+    term.write("\x1b[2;1Hx\x1b[S".to_string());
+    // The change on the second row moved up with it
+    assert_eq!(rows(&term), vec![Some(0..1), None, None, Some(0..WIDTH)]);
+    assert_eq!(term.framebuffer().damage.scrolled, 1);
+}
+
+#[test]
+fn line_feed_at_the_bottom_scrolls() {
+    let mut term = drawn();
+    term.write("\r\nfive".to_string());
+    // Only the new bottom row has to be drawn, the rest is what was on screen one row lower
+    assert_eq!(rows(&term), vec![None, None, None, Some(0..WIDTH)]);
+    assert_eq!(term.framebuffer().damage.scrolled, 1);
+}
+
+#[test]
+fn scrolling_down_is_negative() {
+    let mut term = drawn();
+    term.write("\x1b[2T".to_string());
+    assert_eq!(rows(&term), vec![Some(0..WIDTH), Some(0..WIDTH), None, None]);
+    assert_eq!(term.framebuffer().damage.scrolled, -2);
+}
+
+#[test]
+fn scrolling_back_and_forth_cancels_out() {
+    let mut term = drawn();
+    term.write("\x1b[S\x1b[T".to_string());
+    assert_eq!(term.framebuffer().damage.scrolled, 0);
+    // The top row came back blank while the others are back where they started
+    assert_eq!(rows(&term), vec![Some(0..WIDTH), None, None, None]);
+}
+
+#[test]
+fn scrolling_past_the_screen_damages_everything() {
+    let mut term = drawn();
+    term.write("\x1b[100S".to_string());
+    assert_eq!(rows(&term), vec![Some(0..WIDTH); HEIGHT]);
+    assert_eq!(term.framebuffer().damage.scrolled, HEIGHT as isize);
+}
+
+#[test]
+fn insert_line_damages_rows_below() {
+    let mut term = drawn();
+    term.write("\x1b[3H\x1b[L".to_string());
+    assert_eq!(rows(&term), vec![None, None, Some(0..WIDTH), Some(0..WIDTH)]);
+    assert_eq!(term.framebuffer().damage.scrolled, 0);
+}
+
+#[test]
+fn resize_damages_everything() {
+    let mut term = drawn();
+    term.framebuffer().request_resize(6, 3);
+    term.completed_render();
+    assert_eq!(rows(&term), vec![Some(0..6); 3]);
+}
@@ -0,0 +1,27 @@
+//! Checks how pty output is turned into text before it reaches the terminal.
+
+use rust_terminal::tty::Utf8Decoder;
+
+#[test]
+fn sequences_split_between_reads_are_joined() {
+    let bytes = "a─é😀b".as_bytes();
+    for split in 0..=bytes.len() {
+        let mut decoder = Utf8Decoder::default();
+        let mut text = decoder.decode(&bytes[..split]);
+        text += &decoder.decode(&bytes[split..]);
+        assert_eq!(text, "a─é😀b", "split at {split}");
+    }
+}
+
+#[test]
+fn invalid_bytes_are_replaced() {
+    let mut decoder = Utf8Decoder::default();
+    assert_eq!(decoder.decode(b"a\xffb\xc3(\x80"), "a\u{fffd}b\u{fffd}(\u{fffd}");
+}
+
+#[test]
+fn a_cut_off_sequence_that_never_ends_is_replaced() {
+    let mut decoder = Utf8Decoder::default();
+    assert_eq!(decoder.decode(b"a\xe2\x94"), "a");
+    assert_eq!(decoder.decode(b"b"), "\u{fffd}b");
+}
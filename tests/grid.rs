@@ -0,0 +1,83 @@
+//! Checks the row storage shared by the screen and history.
+
+use rust_terminal::color::CellColor;
+use rust_terminal::grid::Grid;
+use rust_terminal::term::{CharacterCell, Flags};
+
+fn cell(ch: char) -> CharacterCell {
+    CharacterCell::new(ch, CellColor::FG, CellColor::BG, Flags::default())
+}
+
+/// A 3 column grid whose screen rows are filled with `a`, `b`, `c`...
+fn grid(height: usize, history_limit: usize) -> Grid {
+    let mut grid = Grid::new(3, height, history_limit, cell(' '));
+    for y in 0..height {
+        grid[y] = vec![cell((b'a' + y as u8) as char); 3];
+    }
+    grid
+}
+
+/// First character of every line, history and then the screen
+fn lines(grid: &Grid) -> String {
+    (0..grid.history_len() + grid.height()).map(|i| grid.line(i).unwrap()[0].ch).collect()
+}
+
+#[test]
+fn full_screen_scroll_keeps_history() {
+    let mut grid = grid(3, 10);
+    assert_eq!(grid.scroll_up(0..3, 2, cell('.')), 2);
+    assert_eq!(grid.history_len(), 2);
+    assert_eq!(lines(&grid), "abc..");
+    assert_eq!(grid[0][0].ch, 'c');
+}
+
+#[test]
+fn history_is_recycled_at_the_limit() {
+    let mut grid = grid(3, 1);
+    grid.scroll_up(0..3, 1, cell('x'));
+    grid.scroll_up(0..3, 1, cell('y'));
+    assert_eq!(lines(&grid), "bcxy");
+    assert!(grid[2].iter().all(|cell| cell.ch == 'y'));
+}
+
+#[test]
+fn region_scroll_up_leaves_history_alone() {
+    let mut grid = grid(5, 10);
+    assert_eq!(grid.scroll_up(1..4, 1, cell('.')), 0);
+    assert_eq!(grid.history_len(), 0);
+    assert_eq!(lines(&grid), "acd.e");
+}
+
+#[test]
+fn region_scroll_down() {
+    let mut grid = grid(5, 10);
+    grid.scroll_down(1..4, 2, cell('.'));
+    assert_eq!(lines(&grid), "a..be");
+}
+
+#[test]
+fn scroll_is_limited_to_the_region() {
+    let mut grid = grid(4, 10);
+    grid.scroll_down(2..4, 100, cell('.'));
+    assert_eq!(lines(&grid), "ab..");
+    grid.scroll_up(0..4, 100, cell('-'));
+    assert_eq!(lines(&grid), "ab..----");
+}
+
+#[test]
+fn resize_moves_rows_into_history() {
+    let mut grid = grid(4, 10);
+    grid.resize(5, 2, 1, cell('.'));
+    assert_eq!(lines(&grid), "abc");
+    assert_eq!(grid.history_len(), 1);
+    assert_eq!(grid[0].len(), 5);
+    // History keeps its old width
+    assert_eq!(grid.line(0).unwrap().len(), 3);
+}
+
+#[test]
+fn resize_adds_rows_at_the_bottom() {
+    let mut grid = grid(2, 10);
+    grid.resize(3, 4, 0, cell('.'));
+    assert_eq!(lines(&grid), "ab..");
+}
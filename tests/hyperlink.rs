@@ -0,0 +1,60 @@
+//! Checks which cells hyperlinks end up in and that they are freed once no cell uses them.
+
+use std::collections::HashSet;
+use rust_terminal::hyperlink::{Hyperlink, Hyperlinks};
+use rust_terminal::rust_ansi::term::Term;
+use rust_terminal::term::Terminal;
+
+fn link(uri: &str) -> Hyperlink {
+    Hyperlink { uri: uri.to_string(), id: None }
+}
+
+#[test]
+fn freed_ids_are_reused() {
+    let mut links = Hyperlinks::default();
+    let a = links.intern(link("a")).unwrap();
+    let b = links.intern(link("b")).unwrap();
+    links.retain(&HashSet::from([b]));
+    assert_eq!(links.count(), 1);
+    assert!(links.get(a).is_none());
+    assert_eq!(links.get(b).unwrap().uri, "b");
+
+    let c = links.intern(link("c")).unwrap();
+    assert_eq!(c, a);
+    assert_eq!(links.get(c).unwrap().uri, "c");
+}
+
+#[test]
+fn freed_links_with_an_id_get_a_new_one() {
+    let mut links = Hyperlinks::default();
+    let named = Hyperlink { uri: "a".to_string(), id: Some("x".to_string()) };
+    let id = links.intern(named.clone()).unwrap();
+    assert_eq!(links.intern(named.clone()), Some(id));
+    links.retain(&HashSet::new());
+    assert_eq!(links.count(), 0);
+    let id = links.intern(named).unwrap();
+    assert_eq!(links.get(id).unwrap().id.as_deref(), Some("x"));
+}
+
+#[test]
+fn links_leaving_history_are_freed() {
+    let mut term = Term::new(Box::new(Terminal::with_history_limit(20, 4, 10)));
+    for i in 0..20_000 {
+        term.write(format!("\x1b]8;;file:///{i}\x1b\\{i}\x1b]8;;\x1b\\\r\n"));
+    }
+    let cells = term.framebuffer();
+    // Bounded by how often unused links are collected, not by how many were printed
+    assert!(cells.hyperlinks.count() < 5_000, "{} links kept", cells.hyperlinks.count());
+    let last = cells.grid[2][0].hyperlink().unwrap();
+    assert_eq!(cells.hyperlinks.get(last).unwrap().uri, "file:///19999");
+}
+
+#[test]
+fn erased_and_inserted_cells_are_not_links() {
+    let mut term = Term::new(Box::new(Terminal::new(10, 4)));
+    term.write("\x1b]8;;file:///a\x1b\\ab\x1b[K\r\n\x1b[L".to_string());
+    let cells = term.framebuffer();
+    assert!(cells.grid[0][1].hyperlink().is_some());
+    assert!(cells.grid[0][2].hyperlink().is_none());
+    assert!(cells.grid[1].iter().all(|cell| cell.hyperlink().is_none()));
+}
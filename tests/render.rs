@@ -0,0 +1,146 @@
+//! Paints the terminal into the in-memory `SoftwareBackend` and checks the pixels. The backend
+//! draws every character other than a space as a block covering the middle half of its cell,
+//! so the centre of a cell shows the foreground color and its corner the background.
+
+mod common;
+
+use rust_terminal::color::{CellColor, Color, BG_COL, FG_COL};
+use rust_terminal::render::{DrawTarget, Overlays, Painter, Rect, RenderBackend, SoftwareBackend, View};
+use rust_terminal::rust_ansi::term::Term;
+use rust_terminal::selection::{Point, SelectionRange};
+use rust_terminal::term::CharacterCellManager;
+
+const WIDTH: usize = 10;
+const HEIGHT: usize = 4;
+const CELL_WIDTH: u32 = 8;
+const CELL_HEIGHT: u32 = 16;
+
+fn view(focused: bool) -> View {
+    View { width: WIDTH, height: HEIGHT, display_offset: 0, focused, cursor_blink_on: true }
+}
+
+fn paint(term: &Term<CharacterCellManager>, overlays: &Overlays, focused: bool) -> SoftwareBackend {
+    let mut backend = SoftwareBackend::new();
+    Painter::new(CELL_WIDTH, CELL_HEIGHT).paint(&mut backend, term.framebuffer(), overlays, &view(focused));
+    backend
+}
+
+/// Colors are compared without alpha, frame pixels are always opaque
+fn rgb(color: Color) -> (u8, u8, u8) {
+    (color.r, color.g, color.b)
+}
+
+fn centre(backend: &SoftwareBackend, x: u32, y: u32) -> (u8, u8, u8) {
+    rgb(backend.pixel(x * CELL_WIDTH + CELL_WIDTH / 2, y * CELL_HEIGHT + CELL_HEIGHT / 2))
+}
+
+fn corner(backend: &SoftwareBackend, x: u32, y: u32) -> (u8, u8, u8) {
+    rgb(backend.pixel(x * CELL_WIDTH, y * CELL_HEIGHT))
+}
+
+#[test]
+fn frame_covers_the_view() {
+    let backend = paint(&common::terminal(WIDTH, HEIGHT), &Overlays::default(), true);
+    assert_eq!((backend.width(), backend.height()), (WIDTH as u32 * CELL_WIDTH, HEIGHT as u32 * CELL_HEIGHT));
+    assert_eq!(backend.pixels().len(), (backend.width() * backend.height()) as usize * 4);
+}
+
+#[test]
+fn text_is_drawn_in_its_colors() {
+    let term = common::run(WIDTH, HEIGHT, b"\x1b[31;44mA\x1b[m");
+    let backend = paint(&term, &Overlays::default(), true);
+    assert_eq!(centre(&backend, 0, 0), rgb(CellColor::Indexed(1).rgb()));
+    assert_eq!(corner(&backend, 0, 0), rgb(CellColor::Indexed(4).rgb()));
+    assert_eq!(centre(&backend, 2, 0), rgb(BG_COL));
+}
+
+#[test]
+fn block_cursor_swaps_colors() {
+    let term = common::run(WIDTH, HEIGHT, b"A\x1b[2D");
+    let backend = paint(&term, &Overlays::default(), true);
+    assert_eq!(centre(&backend, 0, 0), rgb(BG_COL));
+    assert_eq!(corner(&backend, 0, 0), rgb(FG_COL));
+}
+
+#[test]
+fn unfocused_cursor_is_hollow() {
+    let term = common::run(WIDTH, HEIGHT, b"A");
+    let backend = paint(&term, &Overlays::default(), false);
+    assert_eq!(corner(&backend, 1, 0), rgb(FG_COL));
+    assert_eq!(centre(&backend, 1, 0), rgb(BG_COL));
+    let bottom_right = backend.pixel(2 * CELL_WIDTH - 1, CELL_HEIGHT - 1);
+    assert_eq!(rgb(bottom_right), rgb(FG_COL));
+}
+
+#[test]
+fn selection_inverts_colors() {
+    let term = common::run(WIDTH, HEIGHT, b"AB");
+    let selection = SelectionRange { start: Point::new(0, 0), end: Point::new(0, 0), block: false };
+    let backend = paint(&term, &Overlays { selection: Some(selection), ..Overlays::default() }, true);
+    assert_eq!(corner(&backend, 0, 0), rgb(FG_COL));
+    assert_eq!(centre(&backend, 0, 0), rgb(BG_COL));
+    assert_eq!(corner(&backend, 1, 0), rgb(BG_COL));
+}
+
+#[test]
+fn hovered_link_is_underlined() {
+    let term = common::run(WIDTH, HEIGHT, b"\x1b]8;;https://example.com\x1b\\L\x1b]8;;\x1b\\\r\n");
+    let link = term.framebuffer().grid[0][0].hyperlink();
+    assert!(link.is_some());
+    let underline = |backend: &SoftwareBackend| rgb(backend.pixel(0, CELL_HEIGHT - 1));
+
+    assert_eq!(underline(&paint(&term, &Overlays::default(), true)), rgb(BG_COL));
+    let hovered = paint(&term, &Overlays { hovered_link: link, ..Overlays::default() }, true);
+    assert_eq!(underline(&hovered), rgb(FG_COL));
+}
+
+#[test]
+fn scrolled_frame_matches_a_full_redraw() {
+    let mut term = common::run(WIDTH, HEIGHT, b"one\r\ntwo\r\nthree\r\nfour");
+    let mut painter = Painter::new(CELL_WIDTH, CELL_HEIGHT);
+    let mut backend = SoftwareBackend::new();
+    painter.paint(&mut backend, term.framebuffer(), &Overlays::default(), &view(true));
+    term.completed_render();
+
+    term.write("\r\nfive\r\n\x1b[32msix".to_string());
+    assert_eq!(term.framebuffer().damage.scrolled, 2);
+    painter.paint(&mut backend, term.framebuffer(), &Overlays::default(), &view(true));
+    assert!(backend.pixels() == paint(&term, &Overlays::default(), true).pixels());
+}
+
+/// Counts how often the painter starts drawing and what it draws
+#[derive(Default)]
+struct Counting {
+    draws: usize,
+    fills: usize,
+}
+
+impl RenderBackend for Counting {
+    fn resize(&mut self, _width: u32, _height: u32) -> bool {
+        false
+    }
+
+    fn scroll(&mut self, _pixels: i32) {}
+
+    fn draw(&mut self, draw: &mut dyn FnMut(&mut dyn DrawTarget)) {
+        self.draws += 1;
+        draw(self);
+    }
+}
+
+impl DrawTarget for Counting {
+    fn fill_rect(&mut self, _rect: Rect, _color: Color) {
+        self.fills += 1;
+    }
+
+    fn draw_char(&mut self, _cell: Rect, _ch: char, _fgc: Color, _bgc: Color, _bold: bool) {}
+}
+
+#[test]
+fn a_frame_is_drawn_in_one_go() {
+    let term = common::run(WIDTH, HEIGHT, b"one\r\ntwo");
+    let mut backend = Counting::default();
+    Painter::new(CELL_WIDTH, CELL_HEIGHT).paint(&mut backend, term.framebuffer(), &Overlays::default(), &view(true));
+    assert_eq!(backend.draws, 1);
+    assert!(backend.fills >= WIDTH * HEIGHT);
+}
@@ -0,0 +1,76 @@
+//! Input that used to crash the terminal, and random garbage. Nothing here checks the screen,
+//! only that the terminal survives. The fuzz targets in `fuzz/` look for more of these.
+mod common;
+
+use common::run;
+
+fn survives(width: usize, height: usize, input: &[u8]) {
+    let term = run(width, height, input);
+    let cells = term.framebuffer();
+    let _ = cells.take_events();
+    assert!(cells.cursor().x < width && cells.cursor().y < height);
+}
+
+#[test]
+fn truncated_sgr_colors() {
+    for input in ["\x1b[38m", "\x1b[48m", "\x1b[38;5m", "\x1b[48;5m", "\x1b[38;2m", "\x1b[38;2;1m", "\x1b[48;2;1;2m"] {
+        survives(10, 4, input.as_bytes());
+    }
+}
+
+#[test]
+fn bad_sgr_colors() {
+    for input in ["\x1b[38;9m", "\x1b[38;5;256m", "\x1b[48;5;99999999999m", "\x1b[38;2;999;999;999m"] {
+        survives(10, 4, input.as_bytes());
+    }
+}
+
+#[test]
+fn unknown_erase() {
+    survives(10, 4, b"abc\x1b[9J\x1b[9K");
+}
+
+#[test]
+fn insert_line_off_screen() {
+    survives(10, 4, b"\x1b[99;99H\x1b[L\x1b[0;0H\x1b[99999L");
+}
+
+#[test]
+fn huge_counts() {
+    survives(10, 4, b"\x1b[99999999999999999999A\x1b[99999999999999999999B\x1b[99999999999999999999C\x1b[99999999999999999999D");
+    survives(10, 4, b"\x1b[999999999S\x1b[999999999T\x1b[999999999999;999999999999H");
+}
+
+#[test]
+fn unsupported_sequences() {
+    survives(10, 4, b"\x1b[2;5s\x1b[6n\x1b[5n\x1b[c\x1b[>c\x1b[?9999h");
+}
+
+#[test]
+fn tiny_screen() {
+    survives(1, 1, b"abc\r\n\x1b[2J\x1b[L\x1b[1;1Hx\x1b[K\x1b[1K\x1b[J\x1b[1J");
+}
+
+#[test]
+fn random_bytes() {
+    // xorshift, so failures can be reproduced
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    // Mostly escape sequence material so the parser gets past the introducers
+    const ALPHABET: &[u8] = b"\x1b[]?;0123456789;;;mHJKLSTABCDfGdnrsthlq \x07\x08\n\r\tab\xc3\xa9";
+    for _ in 0..200 {
+        let len = next() as usize % 400;
+        let input: Vec<u8> = (0..len)
+            .map(|_| match next() % 10 {
+                0 => next() as u8,
+                _ => ALPHABET[next() as usize % ALPHABET.len()],
+            })
+            .collect();
+        survives(1 + next() as usize % 20, 1 + next() as usize % 10, &input);
+    }
+}
@@ -0,0 +1,120 @@
+//! Golden-file tests for the screen after a byte stream. The expected screens live in
+//! `tests/snapshots/`, run with `BLESS=1` to rewrite them after a deliberate change. Cases
+//! using `check_known_failure` also have a hand-written `.expected` file with the correct output
+//! for bugs that aren't fixed yet.
+mod common;
+
+use common::{check, check_known_failure};
+
+#[test]
+fn plain_text() {
+    check("plain_text", 10, 4, b"hello\r\nworld");
+}
+
+// LF keeps the column, but `Terminal` treats it as CR LF
+#[test]
+fn line_feed_moves_down_in_same_column() {
+    check_known_failure("line_feed_moves_down_in_same_column", 10, 4, b"ab\ncd\r\nef");
+}
+
+// A row wraps when a character follows a full row, `Terminal` wraps a column early
+#[test]
+fn wrap() {
+    check_known_failure("wrap", 5, 4, b"abcdefghij");
+}
+
+#[test]
+fn cursor_position() {
+    check("cursor_position", 10, 5, b"\x1b[2;3HA\x1b[4;8HB\x1b[HC\x1b[5;1HD");
+}
+
+#[test]
+fn cursor_relative() {
+    check("cursor_relative", 10, 5, b"\x1b[3;3HX\x1b[AU\x1b[2BD\x1b[CR\x1b[5DL");
+}
+
+#[test]
+fn cursor_clamped() {
+    check("cursor_clamped", 10, 5, b"\x1b[3;3H\x1b[20AU\x1b[20BD");
+}
+
+#[test]
+fn cursor_column_and_row() {
+    check("cursor_column_and_row", 10, 5, b"\x1b[3GA\x1b[4dB\x1b[7GC");
+}
+
+#[test]
+fn carriage_return() {
+    check("carriage_return", 10, 3, b"abcdef\rXY");
+}
+
+#[test]
+fn erase_line() {
+    check(
+        "erase_line",
+        10,
+        3,
+        b"01234567\x1b[1;5H\x1b[K\x1b[2;1H01234567\x1b[2;5H\x1b[1K\x1b[3;1H01234567\x1b[3;5H\x1b[2K",
+    );
+}
+
+#[test]
+fn erase_display_below() {
+    check("erase_display_below", 6, 4, b"aaaa\r\nbbbb\r\ncccc\r\ndddd\x1b[2;3H\x1b[J");
+}
+
+#[test]
+fn erase_display_above() {
+    check("erase_display_above", 6, 4, b"aaaa\r\nbbbb\r\ncccc\r\ndddd\x1b[3;3H\x1b[1J");
+}
+
+#[test]
+fn erase_display_all() {
+    check("erase_display_all", 6, 4, b"aaaa\r\nbbbb\r\ncccc\x1b[2J");
+}
+
+#[test]
+fn scroll_into_history() {
+    check("scroll_into_history", 6, 3, b"one\r\ntwo\r\nthree\r\nfour\r\nfive");
+}
+
+#[test]
+fn scroll_up_and_down() {
+    check("scroll_up_and_down", 6, 4, b"a\r\nb\r\nc\r\nd\x1b[S\x1b[2T");
+}
+
+#[test]
+fn insert_line() {
+    check("insert_line", 6, 4, b"a\r\nb\r\nc\x1b[2;1H\x1b[L");
+}
+
+#[test]
+fn sgr_colors() {
+    check("sgr_colors", 20, 3, b"\x1b[31mred\x1b[42mgreen\x1b[0m plain\r\n\x1b[94mbright\x1b[39;49mdef");
+}
+
+#[test]
+fn sgr_bold() {
+    check("sgr_bold", 24, 3, b"\x1b[1mbold\x1b[31mred\x1b[0m plain \x1b[1;32mgreen\x1b[m");
+}
+
+// The color cube starts at 0, `Terminal` maps the first step to 55 like the ones after it
+#[test]
+fn sgr_extended_colors() {
+    check_known_failure("sgr_extended_colors", 12, 3, b"\x1b[38;5;196ma\x1b[38;5;46mb\x1b[48;5;240mc\x1b[38;2;1;2;3md\x1b[48;2;250;128;0me");
+}
+
+#[test]
+fn sgr_erase_uses_background() {
+    check("sgr_erase_uses_background", 6, 3, b"ab\x1b[44m\x1b[K\r\n\x1b[41m\x1b[2K");
+}
+
+#[test]
+fn hide_cursor() {
+    check("hide_cursor", 4, 2, b"\x1b[?25l");
+}
+
+#[test]
+fn hyperlink() {
+    check("hyperlink", 12, 2, b"a \x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\ b");
+}